@@ -0,0 +1,264 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fs::{create_dir_all, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use lambda_calculus::Term;
+use serde::{Deserialize, Serialize};
+
+use crate::classifier::Classifier;
+use crate::config;
+use crate::novelty::species_hash;
+use crate::soup::{Soup, Tape};
+
+/// Number of copies of a species at a poll. Species are identified by their id in the
+/// `AbundanceTracker` that recorded them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    pub poll: usize,
+    pub species: usize,
+    pub count: u32,
+}
+
+/// A recorded species.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Species {
+    pub id: usize,
+    pub term: String,
+
+    /// Name of the species, if the classifier recognizes it
+    pub name: Option<String>,
+
+    /// Largest number of copies of the species at any poll
+    pub peak: u32,
+}
+
+/// Number of copies of each species at each poll of a run, recorded sparsely. Species are given
+/// ids in order of first appearance, and a poll only records the species present in the soup,
+/// so memory grows with the number of species that are present at each poll rather than with
+/// every species ever seen times the number of polls.
+#[derive(Debug, Clone, Default)]
+pub struct AbundanceTracker {
+    ids: HashMap<Term, usize>,
+    species: Vec<Term>,
+    peaks: Vec<u32>,
+    records: Vec<Record>,
+    polls: usize,
+}
+
+/// Recorded abundances of the species that pass a filter, see `AbundanceTracker::finish`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Abundances {
+    /// Number of polls
+    pub polls: usize,
+
+    /// Dictionary of the kept species, by id
+    pub species: Vec<Species>,
+
+    /// Records of the kept species, by poll then by species id
+    pub records: Vec<Record>,
+}
+
+/// Writes the abundances of each soup of an ensemble to a directory of its own, see
+/// `--abundances`.
+#[derive(Debug, Clone)]
+pub struct AbundanceRecorder {
+    dir: PathBuf,
+    filter: config::Abundances,
+}
+
+impl AbundanceTracker {
+    pub fn new() -> Self {
+        AbundanceTracker::default()
+    }
+
+    /// Record the number of copies of each species at the next poll. Species that appear at the
+    /// same poll are given ids in the order of their hashes, so that ids do not depend on the
+    /// iteration order of `counts`.
+    pub fn record(&mut self, counts: &HashMap<Term, u32>) {
+        let poll = self.polls;
+        let start = self.records.len();
+        let mut counts = counts.iter().collect::<Vec<_>>();
+        counts.sort_by_cached_key(|(expr, _)| species_hash(expr));
+        for (expr, count) in counts {
+            let id = match self.ids.get(expr) {
+                Some(id) => *id,
+                None => {
+                    self.species.push(expr.clone());
+                    self.peaks.push(0);
+                    self.ids.insert(expr.clone(), self.species.len() - 1);
+                    self.species.len() - 1
+                }
+            };
+            self.peaks[id] = self.peaks[id].max(*count);
+            self.records.push(Record {
+                poll,
+                species: id,
+                count: *count,
+            });
+        }
+        self.records[start..].sort_by_key(|r| r.species);
+        self.polls += 1;
+    }
+
+    /// Record the species of `soup` at the next poll.
+    pub fn record_soup(&mut self, soup: &Soup) {
+        self.record(&soup.expression_counts());
+    }
+
+    /// Keep the species that pass `filter`, naming them with `classifier`.
+    pub fn finish(self, filter: &config::Abundances, classifier: &Classifier) -> Abundances {
+        let mut kept = (0..self.species.len())
+            .filter(|id| self.peaks[*id] >= filter.min_peak)
+            .collect::<Vec<_>>();
+        if let Some(k) = filter.top_k {
+            kept.sort_by_key(|id| (Reverse(self.peaks[*id]), *id));
+            kept.truncate(k);
+            kept.sort();
+        }
+        let set = kept.iter().copied().collect::<HashSet<_>>();
+
+        Abundances {
+            polls: self.polls,
+            species: kept
+                .into_iter()
+                .map(|id| Species {
+                    id,
+                    term: self.species[id].to_string(),
+                    name: classifier.name(&self.species[id]),
+                    peak: self.peaks[id],
+                })
+                .collect(),
+            records: self
+                .records
+                .into_iter()
+                .filter(|r| set.contains(&r.species))
+                .collect(),
+        }
+    }
+}
+
+impl Abundances {
+    /// Write the records as `poll,species,count` lines.
+    pub fn write_records(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "poll,species,count")?;
+        for r in &self.records {
+            writeln!(out, "{},{},{}", r.poll, r.species, r.count)?;
+        }
+        Ok(())
+    }
+
+    /// Write the species dictionary as `id,term,name,peak` lines.
+    pub fn write_species(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "id,term,name,peak")?;
+        for s in &self.species {
+            writeln!(
+                out,
+                "{},{},{},{}",
+                s.id,
+                s.term,
+                s.name.as_deref().unwrap_or_default(),
+                s.peak
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Write the species dictionary to `path`.
+    pub fn write_species_file(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write_species(&mut out)?;
+        out.flush()
+    }
+
+    /// Write the records to `abundances.csv` and the species dictionary to `species.csv` in
+    /// directory `dir`, creating it if needed.
+    pub fn write_to_dir(&self, dir: impl AsRef<Path>) -> io::Result<()> {
+        let dir = dir.as_ref();
+        create_dir_all(dir)?;
+        let mut out = BufWriter::new(File::create(dir.join("abundances.csv"))?);
+        self.write_records(&mut out)?;
+        out.flush()?;
+        self.write_species_file(dir.join("species.csv"))
+    }
+}
+
+impl AbundanceRecorder {
+    /// Record under `dir`, keeping the species that pass `filter`.
+    pub fn new(dir: impl Into<PathBuf>, filter: config::Abundances) -> Self {
+        AbundanceRecorder {
+            dir: dir.into(),
+            filter,
+        }
+    }
+
+    /// Write the abundances recorded by `tracker` for the soup of replicate `id` to the
+    /// directory `replicate-<id>`, see `Abundances::write_to_dir`. Species are named with the
+    /// reduction limits of `reactor`.
+    pub fn write(
+        &self,
+        id: usize,
+        tracker: AbundanceTracker,
+        reactor: &config::Reactor,
+    ) -> io::Result<()> {
+        let classifier = Classifier::new(reactor.reduction_cutoff, reactor.size_cutoff);
+        tracker
+            .finish(&self.filter, &classifier)
+            .write_to_dir(self.dir.join(format!("replicate-{:06}", id)))
+    }
+}
+
+impl Tape {
+    /// Abundances of every species at every recorded snapshot of the soup.
+    pub fn abundances(&self) -> AbundanceTracker {
+        let mut tracker = AbundanceTracker::new();
+        for soup in self.history() {
+            tracker.record_soup(soup);
+        }
+        tracker
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lambda_calculus::{abs, app, Var};
+
+    #[test]
+    fn sparse_records_round_trip() {
+        let (i, k, omega) = (abs(Var(1)), abs(abs(Var(2))), abs(app(Var(1), Var(1))));
+        let polls = [
+            HashMap::from([(i.clone(), 3), (k.clone(), 1)]),
+            HashMap::from([(k, 2)]),
+            HashMap::from([(i, 1), (omega, 4)]),
+        ];
+        let mut tracker = AbundanceTracker::new();
+        for counts in &polls {
+            tracker.record(counts);
+        }
+        let abundances = tracker.finish(&config::Abundances::new(), &Classifier::new(256, 1024));
+        let json = serde_json::to_string(&abundances).unwrap();
+        let abundances: Abundances = serde_json::from_str(&json).unwrap();
+
+        // Only species present at a poll are recorded
+        assert_eq!(abundances.polls, 3);
+        assert_eq!(abundances.records.len(), 5);
+        let peaks: u32 = abundances.species.iter().map(|s| s.peak).sum();
+        assert_eq!(peaks, 3 + 2 + 4);
+        // Every species is kept, so the id of a species is its index in the dictionary
+        for (poll, counts) in polls.iter().enumerate() {
+            let recorded = abundances
+                .records
+                .iter()
+                .filter(|r| r.poll == poll)
+                .map(|r| (abundances.species[r.species].term.clone(), r.count))
+                .collect::<HashMap<_, _>>();
+            let expected = counts
+                .iter()
+                .map(|(expr, count)| (expr.to_string(), *count))
+                .collect::<HashMap<_, _>>();
+            assert_eq!(recorded, expected);
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use crate::interaction::{InteractionMatrix, Product};
+
+/// A binary operation on the elements `0..n`, given by its table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperationTable {
+    n: usize,
+    op: Vec<usize>,
+}
+
+/// Algebraic properties of an operation table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Properties {
+    pub associative: bool,
+    pub commutative: bool,
+
+    /// The identity element, if there is one
+    pub identity: Option<usize>,
+
+    /// Whether every element has a two-sided inverse. Always `false` without an identity.
+    pub inverses: bool,
+
+    /// Names of the known structures the table is isomorphic to, see `known_structures`
+    pub isomorphic_to: Vec<String>,
+}
+
+/// A subset of the species of an interaction matrix that is closed under one rule.
+#[derive(Debug, Clone)]
+pub struct Structure {
+    /// Index of the rule in the interaction matrix
+    pub rule: usize,
+
+    /// Indices of the species in the interaction matrix. Element `i` of `table` is species
+    /// `members[i]`.
+    pub members: Vec<usize>,
+
+    pub table: OperationTable,
+    pub properties: Properties,
+}
+
+impl OperationTable {
+    /// Create the table of `op` on `0..n`. `op` must map into `0..n`.
+    pub fn new(n: usize, op: impl Fn(usize, usize) -> usize) -> Self {
+        let op = (0..n)
+            .flat_map(|a| (0..n).map(move |b| (a, b)))
+            .map(|(a, b)| op(a, b))
+            .collect();
+        OperationTable { n, op }
+    }
+
+    /// The cyclic group of order `n`, addition modulo `n`.
+    pub fn cyclic(n: usize) -> Self {
+        OperationTable::new(n, |a, b| (a + b) % n)
+    }
+
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    pub fn get(&self, a: usize, b: usize) -> usize {
+        self.op[a * self.n + b]
+    }
+
+    /// The table as rows, where `rows()[a][b]` is the product of `a` and `b`.
+    pub fn rows(&self) -> Vec<Vec<usize>> {
+        self.op
+            .chunks(self.len().max(1))
+            .map(|r| r.to_vec())
+            .collect()
+    }
+
+    pub fn is_associative(&self) -> bool {
+        let n = self.n;
+        (0..n).all(|a| {
+            (0..n)
+                .all(|b| (0..n).all(|c| self.get(self.get(a, b), c) == self.get(a, self.get(b, c))))
+        })
+    }
+
+    pub fn is_commutative(&self) -> bool {
+        (0..self.n).all(|a| (0..a).all(|b| self.get(a, b) == self.get(b, a)))
+    }
+
+    /// The identity element, if there is one. An identity is always unique.
+    pub fn identity(&self) -> Option<usize> {
+        (0..self.n).find(|e| (0..self.n).all(|a| self.get(*e, a) == a && self.get(a, *e) == a))
+    }
+
+    /// Whether every element has a two-sided inverse with respect to the identity.
+    pub fn has_inverses(&self) -> bool {
+        let Some(e) = self.identity() else {
+            return false;
+        };
+        (0..self.n).all(|a| (0..self.n).any(|b| self.get(a, b) == e && self.get(b, a) == e))
+    }
+
+    /// Whether some relabeling of the elements of `self` turns it into `other`.
+    pub fn is_isomorphic_to(&self, other: &OperationTable) -> bool {
+        if self.n != other.n {
+            return false;
+        }
+        let mut map = vec![None; self.n];
+        let mut used = vec![false; self.n];
+        self.extend_isomorphism(other, 0, &mut map, &mut used)
+    }
+
+    /// Try every image for element `a` and the elements after it, given the images of the
+    /// elements before it. Partial maps are checked on every product whose factors and result
+    /// are already mapped.
+    fn extend_isomorphism(
+        &self,
+        other: &OperationTable,
+        a: usize,
+        map: &mut [Option<usize>],
+        used: &mut [bool],
+    ) -> bool {
+        if a == self.n {
+            return true;
+        }
+        for image in 0..self.n {
+            if used[image] {
+                continue;
+            }
+            map[a] = Some(image);
+            used[image] = true;
+            if self.consistent(other, map) && self.extend_isomorphism(other, a + 1, map, used) {
+                return true;
+            }
+            map[a] = None;
+            used[image] = false;
+        }
+        false
+    }
+
+    fn consistent(&self, other: &OperationTable, map: &[Option<usize>]) -> bool {
+        (0..self.n).all(|x| {
+            (0..self.n).all(|y| match (map[x], map[y], map[self.get(x, y)]) {
+                (Some(fx), Some(fy), Some(fxy)) => other.get(fx, fy) == fxy,
+                _ => true,
+            })
+        })
+    }
+
+    /// Compute all properties of the table, comparing it against `known`.
+    pub fn properties(&self, known: &[(String, OperationTable)]) -> Properties {
+        Properties {
+            associative: self.is_associative(),
+            commutative: self.is_commutative(),
+            identity: self.identity(),
+            inverses: self.has_inverses(),
+            isomorphic_to: known
+                .iter()
+                .filter(|(_, t)| self.is_isomorphic_to(t))
+                .map(|(name, _)| name.clone())
+                .collect(),
+        }
+    }
+}
+
+/// Named small groups, monoids and semigroups with between 2 and `max_size` elements: cyclic
+/// groups `Z<n>`, the Klein group `Z2×Z2`, the symmetric group `S3`, the two element monoid `U1`
+/// (`{1, 0}` under multiplication), the null semigroup `O2`, and the left and right zero bands
+/// `L<n>` and `R<n>`.
+pub fn known_structures(max_size: usize) -> Vec<(String, OperationTable)> {
+    let mut known = Vec::new();
+    for n in 2..=max_size {
+        known.push((format!("Z{}", n), OperationTable::cyclic(n)));
+    }
+    if max_size >= 2 {
+        known.push((String::from("U1"), OperationTable::new(2, |a, b| a.max(b))));
+        known.push((String::from("O2"), OperationTable::new(2, |_, _| 0)));
+    }
+    if max_size >= 4 {
+        known.push((String::from("Z2×Z2"), OperationTable::new(4, |a, b| a ^ b)));
+    }
+    if max_size >= 6 {
+        let perms = [
+            [0, 1, 2],
+            [0, 2, 1],
+            [1, 0, 2],
+            [1, 2, 0],
+            [2, 0, 1],
+            [2, 1, 0],
+        ];
+        let compose = |a: usize, b: usize| {
+            let p = [0, 1, 2].map(|i| perms[a][perms[b][i]]);
+            perms.iter().position(|q| *q == p).unwrap()
+        };
+        known.push((String::from("S3"), OperationTable::new(6, compose)));
+    }
+    for n in 2..=max_size {
+        known.push((format!("L{}", n), OperationTable::new(n, |a, _| a)));
+        known.push((format!("R{}", n), OperationTable::new(n, |_, b| b)));
+    }
+    known
+}
+
+/// Call `f` on every subset of `0..n` with `size` elements, in lexicographic order.
+fn for_each_subset(n: usize, size: usize, f: &mut impl FnMut(&[usize])) {
+    fn recurse(
+        start: usize,
+        n: usize,
+        size: usize,
+        acc: &mut Vec<usize>,
+        f: &mut impl FnMut(&[usize]),
+    ) {
+        if acc.len() == size {
+            f(acc);
+            return;
+        }
+        for i in start..n {
+            acc.push(i);
+            recurse(i + 1, n, size, acc, f);
+            acc.pop();
+        }
+    }
+    recurse(0, n, size, &mut Vec::with_capacity(size), f);
+}
+
+/// Find every subset of the species of `matrix` with between 2 and `max_size` elements that is
+/// closed under one of the rules, and classify the operation each rule induces on it.
+pub fn find_structures(matrix: &InteractionMatrix, max_size: usize) -> Vec<Structure> {
+    let known = known_structures(max_size);
+    let mut structures = Vec::new();
+    for rule in 0..matrix.n_rules() {
+        for size in 2..=max_size.min(matrix.len()) {
+            for_each_subset(matrix.len(), size, &mut |members| {
+                let index = |p: Product| match p {
+                    Product::Species(s) => members.iter().position(|m| *m == s),
+                    _ => None,
+                };
+                let closed = members.iter().all(|a| {
+                    members
+                        .iter()
+                        .all(|b| index(matrix.get(*a, *b, rule)).is_some())
+                });
+                if !closed {
+                    return;
+                }
+                let table = OperationTable::new(size, |a, b| {
+                    index(matrix.get(members[a], members[b], rule)).unwrap()
+                });
+                structures.push(Structure {
+                    rule,
+                    members: members.to_vec(),
+                    properties: table.properties(&known),
+                    table,
+                });
+            });
+        }
+    }
+    structures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::soup::Soup;
+    use lambda_calculus::{abs, Var};
+
+    #[test]
+    fn cyclic_group_properties() {
+        let z3 = OperationTable::cyclic(3);
+        let properties = z3.properties(&known_structures(3));
+        assert!(properties.associative && properties.commutative && properties.inverses);
+        assert_eq!(properties.identity, Some(0));
+        assert_eq!(properties.isomorphic_to, vec![String::from("Z3")]);
+    }
+
+    #[test]
+    fn identity_and_false_are_closed() {
+        // Under the default rule `λx.λy.x y`, with I = λ.1 and F = λλ.1: I I = I, I F = F, and
+        // F applied to anything is I. The most frequent species, I, is element 0.
+        let (i, f) = (abs(Var(1)), abs(abs(Var(1))));
+        let mut soup = Soup::new();
+        soup.perturb(vec![i.clone(), i.clone(), i, f]);
+        let matrix = InteractionMatrix::from_soup(&soup, 2);
+
+        let structures = find_structures(&matrix, 2);
+        assert_eq!(structures.len(), 1);
+        let structure = &structures[0];
+        assert_eq!(structure.members, vec![0, 1]);
+        assert_eq!(structure.table.rows(), vec![vec![0, 1], vec![0, 0]]);
+        // (F I) F = F, but F (I F) = I. I is only a left identity.
+        let properties = &structure.properties;
+        assert!(!properties.associative && !properties.commutative);
+        assert_eq!(properties.identity, None);
+        assert!(properties.isomorphic_to.is_empty());
+    }
+}
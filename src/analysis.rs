@@ -1,11 +1,226 @@
 use std::cmp::Reverse;
 use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
 
+use crate::behavior::Prober;
+use crate::complexity::Metric;
+use crate::config;
+use crate::diversity::{self, Base};
 use crate::soup::Soup;
 use crate::utils::HeapObject;
 
 use lambda_calculus::Term;
 
+/// A scalar quantity that can be measured on a soup, usually at every poll. On the command line,
+/// observables are written in kebab case, for example `species-count`, and Hill numbers as
+/// `hill:<q>`, for example `hill:2`. Complexity metrics are written `mean:<metric>` or
+/// `max:<metric>`, for example `mean:redexes`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Observable {
+    /// Population entropy, see `Soup::population_entropy`
+    Entropy,
+
+    /// Number of distinct expressions in the soup, also called `richness`
+    SpeciesCount,
+
+    /// Fraction of reactions that have failed so far
+    FailureRate,
+
+    /// Number of behavioral classes in the soup, on the probes in `probe_config`. See
+    /// `Soup::behavioral_classes`
+    BehavioralClasses,
+
+    /// Shannon entropy in bits
+    ShannonBits,
+
+    /// Shannon entropy in nats
+    ShannonNats,
+
+    /// Simpson's index, see `diversity::simpson`
+    Simpson,
+
+    /// Gini-Simpson index, see `diversity::gini_simpson`
+    GiniSimpson,
+
+    /// Hill number of the given order, see `diversity::hill`
+    Hill(f64),
+
+    /// Pielou's evenness, see `diversity::evenness`
+    Evenness,
+
+    /// Berger-Parker dominance, see `diversity::berger_parker`
+    BergerParker,
+
+    /// Number of products never seen before in the run, since the last poll. Requires novelty
+    /// tracking, see `config::Reactor::track_novelty`
+    Novelty,
+
+    /// Number of distinct species that have appeared since the start of the run. Requires
+    /// novelty tracking
+    CumulativeDistinct,
+
+    /// Number of products never seen before, since the start of the run. Unlike
+    /// `CumulativeDistinct`, the expressions the soup was seeded with are not counted. Requires
+    /// novelty tracking
+    NovelTotal,
+
+    /// Mean of a complexity metric over the expressions of the soup, see `Soup::complexity`
+    MeanComplexity(Metric),
+
+    /// Largest value of a complexity metric over the expressions of the soup
+    MaxComplexity(Metric),
+
+    /// Logarithm of one plus the assembly of the soup, see `Soup::assembly`
+    Assembly,
+
+    /// Assembly index of the expressions of the soup, averaged over every copy
+    AssemblyIndex,
+
+    /// Compressed size of the serialized soup in bytes, see `Soup::compressed_size`
+    CompressedSize,
+
+    /// Ratio of the compressed size of the serialized soup to its raw size
+    CompressionRatio,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseObservableError(String);
+
+impl Observable {
+    /// Whether the observable can only be measured on soups that track novelty.
+    pub fn needs_novelty(&self) -> bool {
+        matches!(
+            self,
+            Observable::Novelty | Observable::CumulativeDistinct | Observable::NovelTotal
+        )
+    }
+
+    /// Measure this observable on `soup`, fingerprinting behavior on `probes`. Observables that
+    /// need novelty tracking are `NaN` on soups that do not track novelty.
+    pub fn measure(&self, soup: &Soup, probes: &config::Probes) -> f32 {
+        match self {
+            Observable::Entropy => soup.population_entropy(),
+            Observable::SpeciesCount => soup.unique_expressions().len() as f32,
+            Observable::FailureRate => soup.failure_rate(),
+            Observable::BehavioralClasses => {
+                let prober = Prober::from_config(probes);
+                soup.behavioral_classes(&prober).len() as f32
+            }
+            Observable::ShannonBits => diversity::shannon(&soup.abundances(), Base::Bits) as f32,
+            Observable::ShannonNats => diversity::shannon(&soup.abundances(), Base::Nats) as f32,
+            Observable::Simpson => diversity::simpson(&soup.abundances()) as f32,
+            Observable::GiniSimpson => diversity::gini_simpson(&soup.abundances()) as f32,
+            Observable::Hill(q) => diversity::hill(&soup.abundances(), *q) as f32,
+            Observable::Evenness => diversity::evenness(&soup.abundances()) as f32,
+            Observable::BergerParker => diversity::berger_parker(&soup.abundances()) as f32,
+            Observable::Novelty => soup.novel_in_interval().map_or(f32::NAN, |n| n as f32),
+            Observable::CumulativeDistinct => {
+                soup.cumulative_distinct().map_or(f32::NAN, |n| n as f32)
+            }
+            Observable::NovelTotal => soup.novel_total().map_or(f32::NAN, |n| n as f32),
+            Observable::MeanComplexity(m) => {
+                soup.complexity(*m).mean().map_or(f32::NAN, |x| x as f32)
+            }
+            Observable::MaxComplexity(m) => {
+                soup.complexity(*m).max().map_or(f32::NAN, |x| x as f32)
+            }
+            Observable::Assembly => soup.assembly().log_assembly as f32,
+            Observable::AssemblyIndex => soup.assembly().mean_index as f32,
+            Observable::CompressedSize => soup.compressed_size() as f32,
+            Observable::CompressionRatio => soup.compression_ratio() as f32,
+        }
+    }
+}
+
+impl FromStr for Observable {
+    type Err = ParseObservableError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(m) = s.strip_prefix("mean:") {
+            return m
+                .parse()
+                .map(Observable::MeanComplexity)
+                .map_err(|_| ParseObservableError(s.to_string()));
+        }
+        if let Some(m) = s.strip_prefix("max:") {
+            return m
+                .parse()
+                .map(Observable::MaxComplexity)
+                .map_err(|_| ParseObservableError(s.to_string()));
+        }
+        if let Some(q) = s.strip_prefix("hill:") {
+            return q
+                .parse()
+                .map(Observable::Hill)
+                .map_err(|_| ParseObservableError(s.to_string()));
+        }
+        match s {
+            "entropy" => Ok(Observable::Entropy),
+            "species-count" | "richness" => Ok(Observable::SpeciesCount),
+            "failure-rate" => Ok(Observable::FailureRate),
+            "behavioral-classes" => Ok(Observable::BehavioralClasses),
+            "shannon-bits" => Ok(Observable::ShannonBits),
+            "shannon-nats" => Ok(Observable::ShannonNats),
+            "simpson" => Ok(Observable::Simpson),
+            "gini-simpson" => Ok(Observable::GiniSimpson),
+            "evenness" => Ok(Observable::Evenness),
+            "berger-parker" => Ok(Observable::BergerParker),
+            "novelty" => Ok(Observable::Novelty),
+            "cumulative-distinct" => Ok(Observable::CumulativeDistinct),
+            "novel-total" => Ok(Observable::NovelTotal),
+            "assembly" => Ok(Observable::Assembly),
+            "assembly-index" => Ok(Observable::AssemblyIndex),
+            "compressed-size" => Ok(Observable::CompressedSize),
+            "compression-ratio" => Ok(Observable::CompressionRatio),
+            _ => Err(ParseObservableError(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Observable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Observable::Entropy => write!(f, "entropy"),
+            Observable::SpeciesCount => write!(f, "species-count"),
+            Observable::FailureRate => write!(f, "failure-rate"),
+            Observable::BehavioralClasses => write!(f, "behavioral-classes"),
+            Observable::ShannonBits => write!(f, "shannon-bits"),
+            Observable::ShannonNats => write!(f, "shannon-nats"),
+            Observable::Simpson => write!(f, "simpson"),
+            Observable::GiniSimpson => write!(f, "gini-simpson"),
+            Observable::Hill(q) => write!(f, "hill:{}", q),
+            Observable::Evenness => write!(f, "evenness"),
+            Observable::BergerParker => write!(f, "berger-parker"),
+            Observable::Novelty => write!(f, "novelty"),
+            Observable::CumulativeDistinct => write!(f, "cumulative-distinct"),
+            Observable::NovelTotal => write!(f, "novel-total"),
+            Observable::MeanComplexity(m) => write!(f, "mean:{}", m),
+            Observable::MaxComplexity(m) => write!(f, "max:{}", m),
+            Observable::Assembly => write!(f, "assembly"),
+            Observable::AssemblyIndex => write!(f, "assembly-index"),
+            Observable::CompressedSize => write!(f, "compressed-size"),
+            Observable::CompressionRatio => write!(f, "compression-ratio"),
+        }
+    }
+}
+
+impl fmt::Display for ParseObservableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unknown observable {}, expected one of: entropy, species-count, richness, \
+             failure-rate, behavioral-classes, shannon-bits, shannon-nats, simpson, \
+             gini-simpson, hill:<q>, evenness, berger-parker, novelty, cumulative-distinct, \
+             novel-total, mean:<metric>, max:<metric>, assembly, assembly-index, \
+             compressed-size, compression-ratio",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseObservableError {}
+
 impl Soup {
     // This is expensive, quadratic in the number of expressions. It can
     // probably be written to be faster, but it's not a bottleneck right now.
@@ -44,26 +259,14 @@ impl Soup {
             .collect()
     }
 
+    /// Shannon entropy of the species distribution, in dits (base 10).
     pub fn population_entropy(&self) -> f32 {
-        let mut entropy = 0.0;
-        let n = self.len() as f32;
-        for (_, value) in self.expression_counts().iter() {
-            let pi = (*value as f32) / n;
-            entropy -= pi * pi.log10();
-        }
-        entropy
+        diversity::shannon(&self.abundances(), Base::Dits) as f32
     }
 
+    /// Weighted Jaccard index of two soups: the number of copies they share, divided by the
+    /// number of copies in either. Identical soups score 1. See `similarity` for other measures.
     pub fn jacard_index(&self, other: &Soup) -> f32 {
-        let selfcounts = self.expression_counts();
-        let othercounts = other.expression_counts();
-
-        let mut intersection = 0;
-        for (k, v) in selfcounts {
-            if let Some(c) = othercounts.get(&k) {
-                intersection += c.min(&v);
-            }
-        }
-        (intersection as f32) / ((self.len() + other.len()) as f32)
+        self.weighted_jaccard(other) as f32
     }
 }
@@ -0,0 +1,132 @@
+use std::collections::HashSet;
+
+use lambda_calculus::Term;
+
+use crate::motifs::Interner;
+use crate::soup::Soup;
+
+/// Assembly of the soup as a whole, see `Soup::assembly`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Assembly {
+    /// Logarithm of one plus the assembly of the soup, `ln(1 + A)`. The assembly
+    /// `A = sum_i exp(a_i) (n_i - 1) / N` is taken over species `i` with assembly index `a_i`
+    /// and `n_i` copies, out of `N` expressions. Species with a single copy do not contribute,
+    /// and the exponential rewards species that are both complex and abundant. `A` itself
+    /// overflows once an abundant species has an index above 700 or so, its logarithm does not.
+    pub log_assembly: f64,
+
+    /// Assembly index of the expressions of the soup, averaged over every copy
+    pub mean_index: f64,
+}
+
+/// Estimate of the assembly index of `expr`, the least number of joining steps that build it
+/// from variables and a lambda, where anything built along the way can be reused. Every
+/// application joins its two sides, and every abstraction joins a lambda to its body, so
+/// building each distinct compound subterm once is a valid pathway, and its length is an upper
+/// bound on the index. Pathways that join fragments which are not subterms can be shorter.
+pub fn assembly_index(expr: &Term) -> usize {
+    let mut interner = Interner::new();
+    let mut ids = HashSet::new();
+    interner.intern_with(expr, &mut |id| {
+        ids.insert(id);
+    });
+    ids.into_iter().filter(|id| !interner.is_var(*id)).count()
+}
+
+impl Soup {
+    /// Assembly index of every species of the soup, see `assembly_index`, with its number of
+    /// copies.
+    pub fn assembly_indices(&self) -> Vec<(Term, usize, u32)> {
+        self.expression_counts()
+            .into_iter()
+            .map(|(expr, count)| {
+                let index = assembly_index(&expr);
+                (expr, index, count)
+            })
+            .collect()
+    }
+
+    /// Assembly of the soup, weighting the assembly index of each species by its copy number.
+    pub fn assembly(&self) -> Assembly {
+        let indices = self.assembly_indices();
+        let total = indices.iter().map(|(_, _, n)| *n as f64).sum::<f64>();
+        if total == 0.0 {
+            return Assembly {
+                log_assembly: 0.0,
+                mean_index: f64::NAN,
+            };
+        }
+
+        // ln(N + sum_i exp(a_i) (n_i - 1)) by log-sum-exp, so that no term is exponentiated
+        // beyond the largest one
+        let terms = indices
+            .iter()
+            .filter(|(_, _, n)| *n > 1)
+            .map(|(_, a, n)| *a as f64 + (*n as f64 - 1.0).ln())
+            .chain([total.ln()])
+            .collect::<Vec<_>>();
+        let max = terms.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let log_sum = max + terms.iter().map(|t| (t - max).exp()).sum::<f64>().ln();
+
+        let mean_index = indices
+            .iter()
+            .map(|(_, a, n)| *a as f64 * *n as f64)
+            .sum::<f64>();
+        Assembly {
+            log_assembly: log_sum - total.ln(),
+            mean_index: mean_index / total,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lambda_calculus::{abs, app, Var};
+
+    fn identity() -> Term {
+        abs(Var(1))
+    }
+
+    fn soup(expressions: Vec<Term>) -> Soup {
+        let mut soup = Soup::new();
+        soup.perturb(expressions);
+        soup
+    }
+
+    #[test]
+    fn assembly_index_of_small_terms() {
+        assert_eq!(assembly_index(&Var(1)), 0);
+        assert_eq!(assembly_index(&identity()), 1);
+        // λ.1, then (λ.1)(λ.1) reusing it
+        assert_eq!(assembly_index(&app(identity(), identity())), 2);
+        // 1 1, λ.1 1, then the application of the two copies
+        let omega = abs(app(Var(1), Var(1)));
+        assert_eq!(assembly_index(&app(omega.clone(), omega)), 3);
+    }
+
+    #[test]
+    fn assembly_of_soup() {
+        let pair = app(identity(), identity());
+        let soup = soup(vec![identity(), identity(), identity(), pair]);
+        let assembly = soup.assembly();
+        // (e^1 (3 - 1) + e^2 (1 - 1)) / 4
+        let expected = (1.0 + 1f64.exp() / 2.0).ln();
+        assert!((assembly.log_assembly - expected).abs() < 1e-12);
+        // (1 * 3 + 2 * 1) / 4
+        assert_eq!(assembly.mean_index, 1.25);
+    }
+
+    #[test]
+    fn assembly_of_deep_species_is_finite() {
+        let mut deep = Var(1);
+        for _ in 0..1000 {
+            deep = abs(deep);
+        }
+        assert_eq!(assembly_index(&deep), 1000);
+        let assembly = soup(vec![deep.clone(), deep]).assembly();
+        // ln(1 + e^1000 / 2)
+        assert!((assembly.log_assembly - (1000.0 - 2f64.ln())).abs() < 1e-9);
+        assert_eq!(soup(vec![]).assembly().log_assembly, 0.0);
+    }
+}
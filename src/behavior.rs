@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+
+use lambda_calculus::{abs, app, Term, Var};
+
+use crate::config;
+use crate::soup::{reduce_with_limit, Soup};
+
+/// The behavior of an expression on a fixed set of probes: the normal form of the expression
+/// applied to each sequence of probe arguments, or `None` where the reduction did not finish
+/// within the limits. Two expressions with the same fingerprint are indistinguishable by the
+/// probes, even if they are structurally different.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Fingerprint(Vec<Option<Term>>);
+
+/// Computes fingerprints of expressions on the probes described by a `config::Probes`.
+#[derive(Debug, Clone)]
+pub struct Prober {
+    probes: Vec<Vec<Term>>,
+    reduction_limit: usize,
+    size_limit: usize,
+}
+
+/// Expressions of a soup that share a fingerprint, with the number of copies of each.
+#[derive(Debug, Clone)]
+pub struct BehavioralClass {
+    pub fingerprint: Fingerprint,
+    pub members: Vec<(Term, u32)>,
+}
+
+/// The Church numeral `n`, `\f.\x. f (f ... (f x))`.
+pub fn church_numeral(n: usize) -> Term {
+    let mut body = Var(1);
+    for _ in 0..n {
+        body = app(Var(2), body);
+    }
+    abs(abs(body))
+}
+
+/// The Church boolean `b`, `\a.\b. a` for true and `\a.\b. b` for false.
+pub fn church_boolean(b: bool) -> Term {
+    abs(abs(Var(if b { 2 } else { 1 })))
+}
+
+impl Fingerprint {
+    /// Whether at least one probe reduced to a normal form.
+    pub fn is_defined(&self) -> bool {
+        self.0.iter().any(|r| r.is_some())
+    }
+
+    /// Normal forms of each probe, in the order of `Prober::probes`.
+    pub fn results(&self) -> &[Option<Term>] {
+        &self.0
+    }
+}
+
+impl Prober {
+    /// Generate a prober from a given `config` object.
+    pub fn from_config(cfg: &config::Probes) -> Self {
+        let mut arguments = cfg
+            .numerals
+            .iter()
+            .map(|n| church_numeral(*n))
+            .collect::<Vec<_>>();
+        if cfg.booleans {
+            arguments.extend([church_boolean(true), church_boolean(false)]);
+        }
+
+        let mut probes = vec![Vec::new()];
+        for _ in 0..cfg.arity {
+            probes = probes
+                .into_iter()
+                .flat_map(|probe| {
+                    arguments.iter().map(move |arg| {
+                        let mut probe = probe.clone();
+                        probe.push(arg.clone());
+                        probe
+                    })
+                })
+                .collect();
+        }
+
+        Prober {
+            probes,
+            reduction_limit: cfg.reduction_cutoff,
+            size_limit: cfg.size_cutoff,
+        }
+    }
+
+    /// Each sequence of arguments an expression is applied to.
+    pub fn probes(&self) -> &[Vec<Term>] {
+        &self.probes
+    }
+
+    /// Apply `expr` to each probe and reduce the result.
+    pub fn fingerprint(&self, expr: &Term) -> Fingerprint {
+        let results = self
+            .probes
+            .iter()
+            .map(|args| {
+                let mut term = args.iter().fold(expr.clone(), |f, arg| app(f, arg.clone()));
+                match reduce_with_limit(&mut term, self.reduction_limit, self.size_limit) {
+                    Ok(n) if n < self.reduction_limit => Some(term),
+                    _ => None,
+                }
+            })
+            .collect();
+        Fingerprint(results)
+    }
+
+    /// Whether `a` and `b` behave the same on every probe. Expressions for which no probe
+    /// finishes are only equivalent if they are isomorphic.
+    pub fn equivalent(&self, a: &Term, b: &Term) -> bool {
+        if a.is_isomorphic_to(b) {
+            return true;
+        }
+        let fingerprint = self.fingerprint(a);
+        fingerprint.is_defined() && fingerprint == self.fingerprint(b)
+    }
+}
+
+impl Soup {
+    /// Group the expressions of the soup by fingerprint. Classes are sorted by decreasing number
+    /// of copies. Expressions for which no probe finished share the undefined class.
+    pub fn behavioral_classes(&self, prober: &Prober) -> Vec<BehavioralClass> {
+        let mut classes = HashMap::<Fingerprint, Vec<(Term, u32)>>::new();
+        for (expr, count) in self.expression_counts() {
+            let fingerprint = prober.fingerprint(&expr);
+            classes.entry(fingerprint).or_default().push((expr, count));
+        }
+
+        let mut classes = classes
+            .into_iter()
+            .map(|(fingerprint, members)| BehavioralClass {
+                fingerprint,
+                members,
+            })
+            .collect::<Vec<_>>();
+        classes.sort_by_key(|c| std::cmp::Reverse(c.count()));
+        classes
+    }
+
+    /// Count the expressions that behave like `target` on every probe, see `Prober::equivalent`.
+    pub fn count_behaving_like(&self, target: &Term, prober: &Prober) -> usize {
+        let fingerprint = prober.fingerprint(target);
+        self.expression_counts()
+            .into_iter()
+            .filter(|(expr, _)| {
+                expr.is_isomorphic_to(target)
+                    || (fingerprint.is_defined() && prober.fingerprint(expr) == fingerprint)
+            })
+            .map(|(_, count)| count as usize)
+            .sum()
+    }
+}
+
+impl BehavioralClass {
+    /// Number of copies of all members of the class.
+    pub fn count(&self) -> u32 {
+        self.members.iter().map(|(_, count)| count).sum()
+    }
+}
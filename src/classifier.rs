@@ -0,0 +1,216 @@
+use std::fmt;
+
+use lambda_calculus::{parse, term::Notation::Classic, Term, Var};
+
+use crate::soup::{reduce_with_limit, Soup};
+
+/// A recognized meaning of an expression. An expression can have several meanings, for example
+/// `\a.\b.a` is both `K` and `true`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Class {
+    /// The Church numeral `n`
+    Numeral(usize),
+
+    /// A Church boolean
+    Boolean(bool),
+
+    /// A Church pair, `\f. f a b`, with the classes of `a` and `b` if they have any
+    Pair(Option<Box<Class>>, Option<Box<Class>>),
+
+    /// A standard combinator, such as `S` or `K`
+    Combinator(&'static str),
+}
+
+/// Recognizes Church numerals, booleans, pairs and standard combinators, up to normal form.
+#[derive(Debug, Clone)]
+pub struct Classifier {
+    /// Combinators that have a normal form, in normal form
+    combinators: Vec<(&'static str, Term)>,
+
+    /// Combinators without a normal form, recognized only as written
+    divergent: Vec<(&'static str, Term)>,
+
+    reduction_limit: usize,
+    size_limit: usize,
+}
+
+impl Classifier {
+    /// Create a classifier that reduces expressions for at most `reduction_limit` steps, and
+    /// gives up on expressions that grow larger than `size_limit`.
+    pub fn new(reduction_limit: usize, size_limit: usize) -> Self {
+        let known = |table: &[(&'static str, &str)]| {
+            table
+                .iter()
+                .map(|(name, expr)| (*name, parse(expr, Classic).unwrap()))
+                .collect::<Vec<_>>()
+        };
+        Classifier {
+            combinators: known(&[
+                ("I", r"\x. x"),
+                ("K", r"\x.\y. x"),
+                ("S", r"\x.\y.\z. x z (y z)"),
+                ("B", r"\x.\y.\z. x (y z)"),
+                ("C", r"\x.\y.\z. x z y"),
+                ("W", r"\x.\y. x y y"),
+                ("ω", r"\x. x x"),
+            ]),
+            divergent: known(&[
+                ("Y", r"\f. (\x. f (x x)) (\x. f (x x))"),
+                ("Θ", r"(\x.\y. y (x x y)) (\x.\y. y (x x y))"),
+                ("Ω", r"(\x. x x) (\x. x x)"),
+            ]),
+            reduction_limit,
+            size_limit,
+        }
+    }
+
+    /// Every class of `expr`. Expressions are reduced to normal form first, except for
+    /// combinators that have none, which are recognized as written.
+    pub fn classify(&self, expr: &Term) -> Vec<Class> {
+        let mut classes = self
+            .divergent
+            .iter()
+            .filter(|(_, t)| t.is_isomorphic_to(expr))
+            .map(|(name, _)| Class::Combinator(name))
+            .collect::<Vec<_>>();
+        if !classes.is_empty() {
+            return classes;
+        }
+
+        let mut normal = expr.clone();
+        match reduce_with_limit(&mut normal, self.reduction_limit, self.size_limit) {
+            Ok(n) if n < self.reduction_limit => (),
+            _ => return classes,
+        }
+
+        classes.extend(
+            self.combinators
+                .iter()
+                .filter(|(_, t)| t.is_isomorphic_to(&normal))
+                .map(|(name, _)| Class::Combinator(name)),
+        );
+        classes.extend(self.classify_data(&normal));
+        classes
+    }
+
+    /// Classes of the Church encoded data `normal`, which must be in normal form.
+    fn classify_data(&self, normal: &Term) -> Vec<Class> {
+        let mut classes = Vec::new();
+        if let Some(n) = as_numeral(normal) {
+            classes.push(Class::Numeral(n));
+        }
+        if let Some(b) = as_boolean(normal) {
+            classes.push(Class::Boolean(b));
+        }
+        if let Some((a, b)) = as_pair(normal) {
+            let first = self.classify_data(&a).into_iter().next();
+            let second = self.classify_data(&b).into_iter().next();
+            let first = first.or_else(|| self.combinator(&a));
+            let second = second.or_else(|| self.combinator(&b));
+            classes.push(Class::Pair(first.map(Box::new), second.map(Box::new)));
+        }
+        classes
+    }
+
+    fn combinator(&self, normal: &Term) -> Option<Class> {
+        self.combinators
+            .iter()
+            .find(|(_, t)| t.is_isomorphic_to(normal))
+            .map(|(name, _)| Class::Combinator(name))
+    }
+
+    /// A name for `expr` made of all its classes, such as `K/true`, or `None` if it has none.
+    pub fn name(&self, expr: &Term) -> Option<String> {
+        let classes = self.classify(expr);
+        if classes.is_empty() {
+            return None;
+        }
+        let names = classes.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        Some(names.join("/"))
+    }
+}
+
+/// Match `\f.\x. f (f ... (f x))`.
+fn as_numeral(expr: &Term) -> Option<usize> {
+    let Term::Abs(outer) = expr else { return None };
+    let Term::Abs(body) = outer.as_ref() else {
+        return None;
+    };
+    let mut n = 0;
+    let mut body = body.as_ref();
+    while let Term::App(app) = body {
+        if app.0 != Var(2) {
+            return None;
+        }
+        body = &app.1;
+        n += 1;
+    }
+    (*body == Var(1)).then_some(n)
+}
+
+/// Match `\a.\b. a` and `\a.\b. b`.
+fn as_boolean(expr: &Term) -> Option<bool> {
+    let Term::Abs(outer) = expr else { return None };
+    match outer.as_ref() {
+        Term::Abs(body) if **body == Var(2) => Some(true),
+        Term::Abs(body) if **body == Var(1) => Some(false),
+        _ => None,
+    }
+}
+
+/// Match `\f. f a b`, where `a` and `b` do not use `f`, and return `a` and `b`.
+fn as_pair(expr: &Term) -> Option<(Term, Term)> {
+    let Term::Abs(body) = expr else { return None };
+    let Term::App(outer) = body.as_ref() else {
+        return None;
+    };
+    let Term::App(inner) = &outer.0 else {
+        return None;
+    };
+    if inner.0 != Var(1) {
+        return None;
+    }
+    Some((unbind(&inner.1, 1)?, unbind(&outer.1, 1)?))
+}
+
+/// Remove the binder `depth` levels above `expr`, or return `None` if `expr` refers to it.
+fn unbind(expr: &Term, depth: usize) -> Option<Term> {
+    match expr {
+        Term::Var(i) if *i == depth => None,
+        Term::Var(i) if *i > depth => Some(Var(i - 1)),
+        Term::Var(i) => Some(Var(*i)),
+        Term::Abs(body) => Some(Term::Abs(Box::new(unbind(body, depth + 1)?))),
+        Term::App(app) => Some(Term::App(Box::new((
+            unbind(&app.0, depth)?,
+            unbind(&app.1, depth)?,
+        )))),
+    }
+}
+
+impl Soup {
+    /// Print out all expressions within the soup, each followed by its name if `classifier`
+    /// recognizes it.
+    pub fn print_named(&self, classifier: &Classifier) {
+        for expression in self.expressions() {
+            match classifier.name(expression) {
+                Some(name) => println!("{}\t{}", expression, name),
+                None => println!("{}", expression),
+            }
+        }
+    }
+}
+
+impl fmt::Display for Class {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let part = |c: &Option<Box<Class>>| match c {
+            Some(c) => c.to_string(),
+            None => String::from("?"),
+        };
+        match self {
+            Class::Numeral(n) => write!(f, "{}", n),
+            Class::Boolean(b) => write!(f, "{}", b),
+            Class::Pair(a, b) => write!(f, "pair({}, {})", part(a), part(b)),
+            Class::Combinator(name) => write!(f, "{}", name),
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+use lambda_calculus::Term;
+use serde::{Deserialize, Serialize};
+
+use crate::soup::{reduce_with_limit, Soup};
+
+/// A per-expression complexity measure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Metric {
+    /// Number of nodes, see `Term::size`
+    Size,
+
+    /// Number of abstractions
+    Abstractions,
+
+    /// Number of applications
+    Applications,
+
+    /// Number of beta redexes, applications of an abstraction
+    Redexes,
+
+    /// Largest number of abstractions enclosing a variable, see `binder_depth_profile`
+    BinderDepth,
+
+    /// Number of distinct free variables
+    FreeVariables,
+
+    /// Number of nodes of the normal form. Undefined if the expression has no normal form
+    /// within the reduction limits.
+    NormalFormSize,
+
+    /// Number of reduction steps to the normal form. Undefined if the expression has no normal
+    /// form within the reduction limits.
+    ReductionSteps,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseMetricError(String);
+
+/// Distribution of a metric over the expressions of a soup, counting every copy.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Distribution {
+    /// Number of expressions with each value of the metric
+    pub histogram: BTreeMap<usize, u32>,
+
+    /// Number of expressions on which the metric is undefined
+    pub undefined: u32,
+}
+
+/// Number of abstractions in `expr`.
+pub fn abstractions(expr: &Term) -> usize {
+    match expr {
+        Term::Var(_) => 0,
+        Term::Abs(body) => 1 + abstractions(body),
+        Term::App(app) => abstractions(&app.0) + abstractions(&app.1),
+    }
+}
+
+/// Number of applications in `expr`.
+pub fn applications(expr: &Term) -> usize {
+    match expr {
+        Term::Var(_) => 0,
+        Term::Abs(body) => applications(body),
+        Term::App(app) => 1 + applications(&app.0) + applications(&app.1),
+    }
+}
+
+/// Number of beta redexes in `expr`, applications whose left side is an abstraction.
+pub fn redexes(expr: &Term) -> usize {
+    match expr {
+        Term::Var(_) => 0,
+        Term::Abs(body) => redexes(body),
+        Term::App(app) => {
+            let redex = matches!(app.0, Term::Abs(_)) as usize;
+            redex + redexes(&app.0) + redexes(&app.1)
+        }
+    }
+}
+
+/// Number of variable occurrences under each number of enclosing abstractions. Element `d` of
+/// the profile counts the variables under exactly `d` abstractions.
+pub fn binder_depth_profile(expr: &Term) -> Vec<usize> {
+    fn visit(expr: &Term, depth: usize, profile: &mut Vec<usize>) {
+        match expr {
+            Term::Var(_) => {
+                if profile.len() <= depth {
+                    profile.resize(depth + 1, 0);
+                }
+                profile[depth] += 1;
+            }
+            Term::Abs(body) => visit(body, depth + 1, profile),
+            Term::App(app) => {
+                visit(&app.0, depth, profile);
+                visit(&app.1, depth, profile);
+            }
+        }
+    }
+    let mut profile = Vec::new();
+    visit(expr, 0, &mut profile);
+    profile
+}
+
+/// Largest number of abstractions enclosing a variable, the last index of the binder depth
+/// profile.
+pub fn binder_depth(expr: &Term) -> usize {
+    binder_depth_profile(expr).len() - 1
+}
+
+/// Number of distinct free variables of `expr`.
+pub fn free_variables(expr: &Term) -> usize {
+    fn visit(expr: &Term, depth: usize, free: &mut HashSet<usize>) {
+        match expr {
+            Term::Var(i) if *i > depth => {
+                free.insert(i - depth);
+            }
+            Term::Var(_) => {}
+            Term::Abs(body) => visit(body, depth + 1, free),
+            Term::App(app) => {
+                visit(&app.0, depth, free);
+                visit(&app.1, depth, free);
+            }
+        }
+    }
+    let mut free = HashSet::new();
+    visit(expr, 0, &mut free);
+    free.len()
+}
+
+/// Reduce `expr` to normal form, within the same limits as a reaction. Returns the normal form
+/// and the number of reduction steps, or `None` if a limit was reached first.
+pub fn normal_form(
+    expr: &Term,
+    reduction_limit: usize,
+    size_limit: usize,
+) -> Option<(Term, usize)> {
+    let mut expr = expr.clone();
+    match reduce_with_limit(&mut expr, reduction_limit, size_limit) {
+        Ok(n) if n < reduction_limit => Some((expr, n)),
+        _ => None,
+    }
+}
+
+impl Metric {
+    /// Every metric, in a fixed order.
+    pub fn all() -> [Metric; 8] {
+        [
+            Metric::Size,
+            Metric::Abstractions,
+            Metric::Applications,
+            Metric::Redexes,
+            Metric::BinderDepth,
+            Metric::FreeVariables,
+            Metric::NormalFormSize,
+            Metric::ReductionSteps,
+        ]
+    }
+
+    /// Measure this metric on `expr`. Normal forms are computed within `reduction_limit` and
+    /// `size_limit`.
+    pub fn measure(&self, expr: &Term, reduction_limit: usize, size_limit: usize) -> Option<usize> {
+        match self {
+            Metric::Size => Some(expr.size()),
+            Metric::Abstractions => Some(abstractions(expr)),
+            Metric::Applications => Some(applications(expr)),
+            Metric::Redexes => Some(redexes(expr)),
+            Metric::BinderDepth => Some(binder_depth(expr)),
+            Metric::FreeVariables => Some(free_variables(expr)),
+            Metric::NormalFormSize => {
+                normal_form(expr, reduction_limit, size_limit).map(|(nf, _)| nf.size())
+            }
+            Metric::ReductionSteps => {
+                normal_form(expr, reduction_limit, size_limit).map(|(_, n)| n)
+            }
+        }
+    }
+}
+
+impl Distribution {
+    /// Number of expressions on which the metric is defined.
+    pub fn defined(&self) -> u32 {
+        self.histogram.values().sum()
+    }
+
+    /// Mean value of the metric, over the expressions on which it is defined.
+    pub fn mean(&self) -> Option<f64> {
+        let n = self.defined();
+        if n == 0 {
+            return None;
+        }
+        let total = self
+            .histogram
+            .iter()
+            .map(|(value, count)| *value as f64 * *count as f64)
+            .sum::<f64>();
+        Some(total / n as f64)
+    }
+
+    /// Largest value of the metric.
+    pub fn max(&self) -> Option<usize> {
+        self.histogram.keys().next_back().copied()
+    }
+}
+
+impl Soup {
+    /// Distribution of `metric` over the expressions of the soup. Normal forms are computed with
+    /// the reduction limits of the soup.
+    pub fn complexity(&self, metric: Metric) -> Distribution {
+        let mut distribution = Distribution::default();
+        for (expr, count) in self.expression_counts() {
+            match metric.measure(&expr, self.reduction_limit(), self.size_limit()) {
+                Some(value) => *distribution.histogram.entry(value).or_default() += count,
+                None => distribution.undefined += count,
+            }
+        }
+        distribution
+    }
+}
+
+impl FromStr for Metric {
+    type Err = ParseMetricError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Metric::all()
+            .into_iter()
+            .find(|m| m.to_string() == s)
+            .ok_or_else(|| ParseMetricError(s.to_string()))
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Metric::Size => write!(f, "size"),
+            Metric::Abstractions => write!(f, "abstractions"),
+            Metric::Applications => write!(f, "applications"),
+            Metric::Redexes => write!(f, "redexes"),
+            Metric::BinderDepth => write!(f, "binder-depth"),
+            Metric::FreeVariables => write!(f, "free-variables"),
+            Metric::NormalFormSize => write!(f, "normal-form-size"),
+            Metric::ReductionSteps => write!(f, "reduction-steps"),
+        }
+    }
+}
+
+impl fmt::Display for ParseMetricError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unknown complexity metric {}, expected one of: size, abstractions, applications, \
+             redexes, binder-depth, free-variables, normal-form-size, reduction-steps",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseMetricError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lambda_calculus::{abs, app, Var};
+
+    /// `λ.1 (λ.1 2)`
+    fn nested() -> Term {
+        abs(app(Var(1), abs(app(Var(1), Var(2)))))
+    }
+
+    /// `λ.1`
+    fn identity() -> Term {
+        abs(Var(1))
+    }
+
+    #[test]
+    fn counts_of_nested_term() {
+        assert_eq!(abstractions(&nested()), 2);
+        assert_eq!(applications(&nested()), 2);
+        assert_eq!(redexes(&nested()), 0);
+        assert_eq!(free_variables(&nested()), 0);
+    }
+
+    #[test]
+    fn free_variables_are_counted_once() {
+        assert_eq!(free_variables(&abs(app(Var(2), Var(1)))), 1);
+        // Both occurrences are the same variable, seen under 0 and 1 binders
+        assert_eq!(free_variables(&app(Var(1), abs(Var(2)))), 1);
+        assert_eq!(free_variables(&app(Var(1), Var(2))), 2);
+    }
+
+    #[test]
+    fn redexes_at_every_level() {
+        // (λ.1) ((λ.1) 1)
+        let expr = app(identity(), app(identity(), Var(1)));
+        assert_eq!(redexes(&expr), 2);
+        assert_eq!(redexes(&abs(app(Var(1), Var(1)))), 0);
+    }
+
+    #[test]
+    fn binder_depth_profile_by_depth() {
+        // 1 under one binder, 1 and 2 under two
+        assert_eq!(binder_depth_profile(&nested()), vec![0, 1, 2]);
+        assert_eq!(binder_depth(&nested()), 2);
+        assert_eq!(binder_depth_profile(&Var(1)), vec![1]);
+        assert_eq!(binder_depth(&Var(1)), 0);
+    }
+
+    #[test]
+    fn normal_form_within_limits() {
+        let expr = app(identity(), identity());
+        assert_eq!(normal_form(&expr, 2, 100), Some((identity(), 1)));
+        assert_eq!(normal_form(&identity(), 1, 100), Some((identity(), 0)));
+        // The one step needed uses up the whole limit
+        assert_eq!(normal_form(&expr, 1, 100), None);
+
+        let omega = app(abs(app(Var(1), Var(1))), abs(app(Var(1), Var(1))));
+        assert_eq!(normal_form(&omega, 10, 100), None);
+
+        // (λ.1 1 1) (λ.1 1 1) grows at every step
+        let triple = abs(app(app(Var(1), Var(1)), Var(1)));
+        assert_eq!(normal_form(&app(triple.clone(), triple), 10, 10), None);
+    }
+}
@@ -0,0 +1,156 @@
+use lambda_calculus::Term;
+use miniz_oxide::deflate::compress_to_vec;
+use serde::{Deserialize, Serialize};
+
+use crate::soup::Soup;
+
+/// DEFLATE compression level, from 0 to 10. Slower levels find more of the redundancy between
+/// expressions, which is what the estimates measure.
+const LEVEL: u8 = 9;
+
+/// Compression of one poll of a soup.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CompressionPoint {
+    /// Length of the serialized soup in bytes, see `Soup::serialize_species`
+    pub raw_size: usize,
+
+    /// Length of the compressed serialization in bytes
+    pub compressed_size: usize,
+
+    /// Normalized compression distance to the previous poll, `None` at the first poll
+    pub ncd: Option<f64>,
+}
+
+/// Compression of each poll of a soup, computed as the soup is polled. Only the expressions of
+/// the previous poll are kept, to measure the distance to the next one.
+#[derive(Debug, Clone, Default)]
+pub struct CompressionTracker {
+    /// Expressions of the previous poll, and their compressed size
+    previous: Option<(Vec<String>, usize)>,
+}
+
+/// Length of `data` compressed with DEFLATE. An upper bound on the algorithmic complexity of
+/// `data`, up to a constant.
+pub fn compressed_size(data: &[u8]) -> usize {
+    compress_to_vec(data, LEVEL).len()
+}
+
+/// Serialize a sorted multiset of expressions, one per line.
+fn serialize(lines: &[String]) -> Vec<u8> {
+    lines.join("\n").into_bytes()
+}
+
+/// Normalized compression distance between two sorted multisets of expressions `x` and `y`,
+/// `(C(xy) - min(C(x), C(y))) / max(C(x), C(y))`, given their compressed sizes `cx` and `cy`.
+/// Close to 0 when one of them says little that the other does not, and close to 1 when they
+/// share no structure. Compressor artifacts can take it slightly outside of `[0, 1]`.
+///
+/// `xy` is the sorted union of the multisets rather than their concatenation. DEFLATE only looks
+/// 32 KiB back, so in a concatenation of two large soups it would never see what they share.
+pub fn ncd(x: &[String], y: &[String], cx: usize, cy: usize) -> f64 {
+    let mut xy = [x, y].concat();
+    xy.sort();
+    let cxy = compressed_size(&serialize(&xy));
+    (cxy as f64 - cx.min(cy) as f64) / cx.max(cy) as f64
+}
+
+impl CompressionTracker {
+    pub fn new() -> Self {
+        CompressionTracker::default()
+    }
+
+    /// Compressed size of the next poll, a sorted multiset of expressions as given by
+    /// `Soup::species_lines`, and its normalized compression distance to the previous poll.
+    pub fn poll(&mut self, lines: Vec<String>) -> CompressionPoint {
+        let data = serialize(&lines);
+        let size = compressed_size(&data);
+        let ncd = self
+            .previous
+            .as_ref()
+            .map(|(previous, previous_size)| ncd(previous, &lines, *previous_size, size));
+        self.previous = Some((lines, size));
+        CompressionPoint {
+            raw_size: data.len(),
+            compressed_size: size,
+            ncd,
+        }
+    }
+}
+
+impl Soup {
+    /// Every expression of the soup, printed, in sorted order. Two soups with the same species
+    /// and counts give the same lines, and copies of a species are adjacent.
+    pub fn species_lines(&self) -> Vec<String> {
+        let mut lines = self.expressions().map(Term::to_string).collect::<Vec<_>>();
+        lines.sort();
+        lines
+    }
+
+    /// Serialize the multiset of expressions of the soup, one copy per line, in sorted order, so
+    /// that the compressor sees the abundances as well as the terms.
+    pub fn serialize_species(&self) -> Vec<u8> {
+        serialize(&self.species_lines())
+    }
+
+    /// Compressed size of the serialized soup, see `compressed_size`. Unlike
+    /// `population_entropy`, this accounts for the structure of the terms and what they share.
+    pub fn compressed_size(&self) -> usize {
+        compressed_size(&self.serialize_species())
+    }
+
+    /// Ratio of the compressed size of the serialized soup to its raw size. Lower ratios mean
+    /// more redundant soups. `NaN` for an empty soup, which has nothing to compress.
+    pub fn compression_ratio(&self) -> f64 {
+        let data = self.serialize_species();
+        if data.is_empty() {
+            return f64::NAN;
+        }
+        compressed_size(&data) as f64 / data.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `n` pseudorandom hexadecimal lines in sorted order. Different seeds share little but the
+    /// alphabet.
+    fn lines(seed: u64, n: usize) -> Vec<String> {
+        let mut state = seed;
+        let mut lines = (0..n)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                format!("{:016x}", state)
+            })
+            .collect::<Vec<_>>();
+        lines.sort();
+        lines
+    }
+
+    fn distance(x: &[String], y: &[String]) -> f64 {
+        let (cx, cy) = (
+            compressed_size(&serialize(x)),
+            compressed_size(&serialize(y)),
+        );
+        ncd(x, y, cx, cy)
+    }
+
+    #[test]
+    fn ncd_to_itself_is_near_zero() {
+        let x = lines(1, 200);
+        // Each line of `x` is followed by its copy in the union, which costs a few bits more
+        assert!(distance(&x, &x) < 0.1);
+    }
+
+    #[test]
+    fn ncd_is_bounded() {
+        let (x, y) = (lines(1, 200), lines(2, 200));
+        let d = distance(&x, &y);
+        // Unrelated lines share almost nothing, and compressor overhead can push the distance
+        // slightly above 1
+        assert!(d > 0.5 && d < 1.1);
+        assert!((0.0..1.1).contains(&distance(&x, &x[..100])));
+    }
+}
@@ -1,7 +1,12 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
 use rand::{thread_rng, Rng};
 
 use serde::{Deserialize, Serialize};
 
+use crate::complexity::Metric;
+use crate::cycles::Grain;
 use crate::generators::Standardization;
 
 use crate::utils::{decode_hex, encode_hex};
@@ -28,11 +33,24 @@ pub struct Config {
 
     /// Configuration options for the lambda reactor.
     pub reactor_config: Reactor,
+
+    /// Configuration options for behavioral fingerprints of expressions.
+    #[serde(default)]
+    pub probe_config: Probes,
+
+    /// Filters for the species abundances written with `--abundances`.
+    #[serde(default)]
+    pub abundance_config: Abundances,
+
+    /// Configuration options for each experiment. Sections that are left out take their
+    /// default values.
+    #[serde(default)]
+    pub experiments: Experiments,
 }
 
 /// Configuration for the reactor
 #[warn(missing_docs)]
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Reactor {
     /// Set of reaction rules. Each rule must always be a lambda expressions
     /// with two arguments. Default: `["\x.\y.x y"]`.
@@ -68,11 +86,16 @@ pub struct Reactor {
     /// The seed for the reactor. If set to `None`, then a seed is chosen
     /// randomly. Default: `None`
     pub seed: ConfigSeed,
+
+    /// When set, keep a hash of every species that has appeared in the soup, so that novel
+    /// products can be counted. Default: `false`.
+    #[serde(default)]
+    pub track_novelty: bool,
 }
 
 /// Configuration for the generators
 #[warn(missing_docs)]
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum Generator {
     /// Use the btree generator
     BTree(BTreeGen),
@@ -81,13 +104,23 @@ pub enum Generator {
     Fontana(FontanaGen),
 }
 
+impl Generator {
+    /// Replace the seed of the generator.
+    pub fn set_seed(&mut self, seed: ConfigSeed) {
+        match self {
+            Generator::BTree(gen) => gen.seed = seed,
+            Generator::Fontana(gen) => gen.seed = seed,
+        }
+    }
+}
+
 pub trait GenConfig {
     fn new() -> Self;
 }
 
 /// Configuration for the BTree generator
 #[warn(missing_docs)]
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BTreeGen {
     /// The seed for the lambda expression generator. If set to `None`, then a seed is chosen
     /// randomly. Default: `None`
@@ -109,7 +142,7 @@ pub struct BTreeGen {
 
 /// Configuration for Fontana's generator
 #[warn(missing_docs)]
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FontanaGen {
     /// The seed for the lambda expression generator. If set to `None`, then a seed is chosen
     /// randomly. Default: `None`
@@ -130,6 +163,383 @@ pub struct FontanaGen {
     pub n_max_free_vars: u32,
 }
 
+/// Configuration for behavioral fingerprints. Each expression is applied to every sequence of
+/// `arity` probe arguments, and the normal forms of the results make up its fingerprint.
+#[warn(missing_docs)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Probes {
+    /// Church numerals used as probe arguments. Default: `[0, 1, 2, 3]`.
+    pub numerals: Vec<usize>,
+
+    /// When set, Church booleans are also used as probe arguments. Default: `true`.
+    pub booleans: bool,
+
+    /// Number of probe arguments each expression is applied to. Default: `2`.
+    pub arity: usize,
+
+    /// Give up on a probe if it takes more than `reduction_cutoff` steps to reduce. Default:
+    /// `256`.
+    pub reduction_cutoff: usize,
+
+    /// Give up on a probe if it grows larger than `size_cutoff` during reduction. Default:
+    /// `1024`.
+    pub size_cutoff: usize,
+}
+
+/// Configuration for species abundance records. Only species that pass both filters are kept.
+#[warn(missing_docs)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Abundances {
+    /// Keep only species that reach at least `min_peak` copies at some poll. Default: `1`.
+    pub min_peak: u32,
+
+    /// When set, keep only the `top_k` species with the highest peak number of copies over the
+    /// run. Default: `None`.
+    pub top_k: Option<usize>,
+}
+
+/// Configuration for an experiment that runs an ensemble of soups. Soups are seeded from the
+/// expression generator in `Config::generator_config`, or from `stdin` for experiments that read
+/// their sample.
+#[warn(missing_docs)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Ensemble {
+    /// Number of soups in the ensemble.
+    pub replicates: usize,
+
+    /// Number of reactions to run in each soup.
+    pub run_limit: usize,
+
+    /// Poll each soup every `polling_interval` reactions.
+    pub polling_interval: usize,
+
+    /// Number of expressions each soup starts with.
+    pub sample_size: usize,
+
+    /// Configuration of the reactor of each soup. The seed is ignored, and replaced by a
+    /// different seed for each soup.
+    pub reactor_config: Reactor,
+
+    /// Criteria for stopping each soup before `run_limit`. Only used by the `sample-simulate`
+    /// and `entropy-series` experiments, so setting a criterion in any other section is an
+    /// error.
+    #[serde(default)]
+    pub stopping: Stopping,
+}
+
+/// Criteria for stopping a run early, checked at every poll. The run stops as soon as any
+/// criterion that is set is met. By default, no criterion is set.
+#[warn(missing_docs)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Stopping {
+    /// Stop when the population entropy at the last `entropy_window` polls stays within
+    /// `entropy_tolerance`. Must be at least 2. Default: `None`.
+    pub entropy_window: Option<usize>,
+
+    /// Largest change of entropy over the window that counts as a plateau. Default: `0.01`.
+    pub entropy_tolerance: f32,
+
+    /// Stop when no product has been novel for this many collisions. Turns on novelty tracking
+    /// in the reactor. Default: `None`.
+    pub novelty_collisions: Option<usize>,
+
+    /// Stop when the set of the `top_k` most frequent species is unchanged for `top_k_polls`
+    /// polls. Default: `None`.
+    pub top_k: Option<usize>,
+
+    /// Number of polls the most frequent species must stay unchanged. Must be at least 1.
+    /// Default: `10`.
+    pub top_k_polls: usize,
+}
+
+/// Configuration for the xorset stability experiment. Soups are seeded with a candidate xorset
+/// `(a, b)` in each of the `proportions`, optionally mixed with background expressions from the
+/// generator in each of the `background_fractions`. Every combination of proportion and
+/// background fraction is run `replicates` times.
+#[warn(missing_docs)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct XorsetStability {
+    /// Ensemble options shared by every condition.
+    #[serde(flatten)]
+    pub ensemble: Ensemble,
+
+    /// First expression of the candidate xorset, in classic notation.
+    pub a: String,
+
+    /// Second expression of the candidate xorset, in classic notation.
+    pub b: String,
+
+    /// Fractions of the xorset expressions that are copies of `a`. The rest are copies of `b`.
+    pub proportions: Vec<f64>,
+
+    /// Fractions of the initial soup drawn from the generator instead of the xorset. Generated
+    /// expressions with free variables are dropped when the soup is seeded, so the actual
+    /// background fraction is somewhat lower than the configured one.
+    pub background_fractions: Vec<f64>,
+
+    /// The xorset dominates a soup once `a` and `b` together make up at least this fraction of
+    /// it.
+    pub dominance: f64,
+}
+
+/// Configuration for the target search experiment. Soups are seeded from the generator, and
+/// polled for copies of each target expression.
+#[warn(missing_docs)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TargetSearch {
+    /// Ensemble options of the search.
+    #[serde(flatten)]
+    pub ensemble: Ensemble,
+
+    /// Target expressions in classic notation, keyed by name. Targets are reduced to normal form
+    /// before the search. Default: Church arithmetic, predicate and pair operations.
+    pub targets: BTreeMap<String, String>,
+
+    /// File with more targets, one per line as a name followed by an expression. Blank lines
+    /// and lines starting with `#` are ignored. Default: `None`.
+    pub targets_file: Option<String>,
+
+    /// When set, an expression counts as a copy of a target if it behaves like the target on
+    /// the probes in `Config::probe_config`, instead of only if it is isomorphic to the target.
+    /// Default: `false`.
+    #[serde(default)]
+    pub match_behavior: bool,
+}
+
+/// Configuration for the replicator search experiment. Soups are seeded from the generator, and
+/// their most frequent species are ranked by replicative behavior at the end of the run.
+#[warn(missing_docs)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReplicatorSearch {
+    /// Ensemble options of the search.
+    #[serde(flatten)]
+    pub ensemble: Ensemble,
+
+    /// Number of most frequent species to rank in each soup.
+    pub top_k: usize,
+}
+
+/// Configuration for the interaction matrix experiment. A single soup is seeded from the
+/// generator, and the products of its most frequent species are tabulated at the end of the run.
+/// Only the first replicate is run.
+#[warn(missing_docs)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Interactions {
+    /// Ensemble options of the experiment.
+    #[serde(flatten)]
+    pub ensemble: Ensemble,
+
+    /// Number of most frequent species in the matrix.
+    pub top_k: usize,
+
+    /// When set, draw a heatmap of the matrix of each rule at `<heatmap>-<rule>.png`. Default:
+    /// `None`.
+    pub heatmap: Option<String>,
+}
+
+/// Configuration for the algebraic structure search. Soups are seeded from the generator, and
+/// subsets of their most frequent species that are closed under a rule are classified at the
+/// end of the run.
+#[warn(missing_docs)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AlgebraSearch {
+    /// Ensemble options of the search.
+    #[serde(flatten)]
+    pub ensemble: Ensemble,
+
+    /// Number of most frequent species to search in each soup.
+    pub top_k: usize,
+
+    /// Largest closed subset to look for. The search is exponential in this size.
+    pub max_size: usize,
+}
+
+/// Configuration for the family tracking experiment. Soups are seeded from the generator, and at
+/// every poll their most frequent species are clustered into families of structurally related
+/// expressions, which are followed from poll to poll.
+#[warn(missing_docs)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Families {
+    /// Ensemble options of the experiment.
+    #[serde(flatten)]
+    pub ensemble: Ensemble,
+
+    /// Number of most frequent species to cluster at each poll.
+    pub top_k: usize,
+
+    /// Largest mean tree edit distance between the members of two clusters that are merged into
+    /// one family. Also the largest distance between the medoids of a family at successive polls.
+    pub max_distance: f64,
+}
+
+/// Configuration for the motif series experiment. Soups are seeded from the generator and
+/// recorded at every poll. The most frequent subterms of the final state are then counted at
+/// every poll.
+#[warn(missing_docs)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Motifs {
+    /// Ensemble options of the experiment.
+    #[serde(flatten)]
+    pub ensemble: Ensemble,
+
+    /// Number of most frequent subterms to follow in each soup.
+    pub top_k: usize,
+
+    /// Smallest number of nodes of a subterm that counts as a motif.
+    pub min_size: usize,
+}
+
+/// Configuration for the cycle detection experiment. Soups are seeded from the generator, and
+/// the fingerprint of their composition is recorded at every poll. Each soup is then checked
+/// for a trajectory that ends in a cycle.
+#[warn(missing_docs)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Cycles {
+    /// Ensemble options of the experiment.
+    #[serde(flatten)]
+    pub ensemble: Ensemble,
+
+    /// How finely fingerprints distinguish species counts. Coarser grains detect approximate
+    /// cycles. Default: `Log2`.
+    pub grain: Grain,
+
+    /// Smallest number of complete periods at the end of a trajectory that counts as a cycle.
+    /// Must be at least 2. Default: `3`.
+    pub min_repeats: usize,
+}
+
+/// How the twin of a soup is perturbed in the perturbation sensitivity experiment.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Perturb {
+    /// Replace one expression by a copy of a different expression of the soup
+    Swap,
+
+    /// Give the twin a different random number generator seed
+    Reseed,
+}
+
+/// Configuration for the perturbation sensitivity experiment. Each soup is seeded from the
+/// generator and run for `warmup` reactions. It is then cloned, the clone is perturbed, and both
+/// copies are run side by side for `run_limit` reactions, comparing them at every poll.
+#[warn(missing_docs)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Perturbation {
+    /// Ensemble options of the experiment.
+    #[serde(flatten)]
+    pub ensemble: Ensemble,
+
+    /// Number of reactions to run before cloning the soup.
+    pub warmup: usize,
+
+    /// How the clone is perturbed. Default: `Swap`.
+    pub perturbation: Perturb,
+
+    /// Number of most frequent species compared between the copies.
+    pub top_k: usize,
+
+    /// Bray-Curtis dissimilarity from which the copies count as separated.
+    pub threshold: f64,
+}
+
+/// Configuration for the complexity histogram experiment. Soups are seeded from the generator,
+/// and the distribution of each metric over their expressions is recorded at every poll.
+#[warn(missing_docs)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Complexity {
+    /// Ensemble options of the experiment.
+    #[serde(flatten)]
+    pub ensemble: Ensemble,
+
+    /// Metrics to record. `NormalFormSize` and `ReductionSteps` reduce every distinct
+    /// expression at every poll, and are much slower than the others. Default: every metric
+    /// except these two.
+    pub metrics: Vec<Metric>,
+}
+
+/// Configuration for the sample scan. A single soup is seeded from `stdin`, and the number of
+/// copies of each species is recorded at every poll.
+#[warn(missing_docs)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SampleScan {
+    /// Ensemble options of the scan. Only the first replicate is run.
+    #[serde(flatten)]
+    pub ensemble: Ensemble,
+
+    /// Filters for the species that are recorded.
+    #[serde(flatten)]
+    pub abundances: Abundances,
+
+    /// When set, write the dictionary of recorded species to this file, as `id,term,name,peak`
+    /// lines. The report itself refers to species by id. Default: `None`.
+    pub species_file: Option<String>,
+}
+
+/// Configuration for each experiment
+#[warn(missing_docs)]
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct Experiments {
+    /// Look for Church addition in soups seeded from `stdin`.
+    pub addition_search: Ensemble,
+
+    /// Look for xorsets in soups seeded from the generator.
+    pub xorset_search: Ensemble,
+
+    /// Final entropy and failure rate of soups seeded from the generator.
+    pub entropy_test: Ensemble,
+
+    /// Final entropy of small soups seeded from the generator.
+    pub sync_entropy_test: Ensemble,
+
+    /// Polled series of soups seeded from the generator.
+    pub entropy_series: Ensemble,
+
+    /// Polled series of soups seeded from `stdin`.
+    pub sample_simulate: Ensemble,
+
+    /// Species counts over time of a single soup seeded from `stdin`. Only the first replicate
+    /// is run.
+    pub sample_scan: SampleScan,
+
+    /// Persistence of a candidate xorset, alone and mixed with generated expressions.
+    pub xorset_stability: XorsetStability,
+
+    /// Look for a set of target expressions in soups seeded from the generator.
+    pub target_search: TargetSearch,
+
+    /// Rank the most frequent species of soups seeded from the generator by how they replicate.
+    pub replicator_search: ReplicatorSearch,
+
+    /// Products of the most frequent species of a soup seeded from the generator.
+    pub interactions: Interactions,
+
+    /// Look for closed algebraic structures among the most frequent species of soups seeded from
+    /// the generator.
+    pub algebra_search: AlgebraSearch,
+
+    /// Follow families of structurally related species in soups seeded from the generator.
+    pub family_tracking: Families,
+
+    /// Follow the most frequent subterms of soups seeded from the generator.
+    pub motif_series: Motifs,
+
+    /// Look for cycles in the composition of soups seeded from the generator.
+    pub cycle_detection: Cycles,
+
+    /// Divergence of perturbed twins of soups seeded from the generator.
+    pub perturbation: Perturbation,
+
+    /// Distribution of complexity metrics in soups seeded from the generator.
+    pub complexity_histograms: Complexity,
+
+    /// Compressed size of soups seeded from the generator, and compression distance between
+    /// successive polls.
+    pub compression_series: Ensemble,
+}
+
 impl Reactor {
     /// Produce a new `ReactorConfig` struct with default values.
     pub fn new() -> Self {
@@ -144,7 +554,405 @@ impl Reactor {
             reduction_cutoff: 500,
             size_cutoff: 500,
             seed: ConfigSeed(None),
+            track_novelty: false,
+        }
+    }
+}
+
+impl Probes {
+    /// Produce a new `Probes` struct with default values.
+    pub fn new() -> Self {
+        Probes {
+            numerals: vec![0, 1, 2, 3],
+            booleans: true,
+            arity: 2,
+            reduction_cutoff: 256,
+            size_cutoff: 1024,
+        }
+    }
+}
+
+impl Default for Probes {
+    fn default() -> Self {
+        Probes::new()
+    }
+}
+
+impl Abundances {
+    /// Produce a new `Abundances` struct with default values.
+    pub fn new() -> Self {
+        Abundances {
+            min_peak: 1,
+            top_k: None,
+        }
+    }
+}
+
+impl Default for Abundances {
+    fn default() -> Self {
+        Abundances::new()
+    }
+}
+
+impl Stopping {
+    /// Produce a new `Stopping` struct with default values, which never stops a run early.
+    pub fn new() -> Self {
+        Stopping {
+            entropy_window: None,
+            entropy_tolerance: 0.01,
+            novelty_collisions: None,
+            top_k: None,
+            top_k_polls: 10,
+        }
+    }
+}
+
+impl Stopping {
+    /// Whether any criterion is set.
+    pub fn is_set(&self) -> bool {
+        self.entropy_window.is_some() || self.novelty_collisions.is_some() || self.top_k.is_some()
+    }
+
+    /// Check that the windows are long enough to compare polls.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(window) = self.entropy_window.filter(|w| *w < 2) {
+            return Err(ConfigError::TooSmall("entropy_window", window, 2));
+        }
+        if self.top_k_polls < 1 {
+            return Err(ConfigError::TooSmall("top_k_polls", self.top_k_polls, 1));
+        }
+        Ok(())
+    }
+}
+
+impl Default for Stopping {
+    fn default() -> Self {
+        Stopping::new()
+    }
+}
+
+impl Ensemble {
+    /// Produce a new `Ensemble` struct with the given run length and number of replicates. The
+    /// reactor keeps copy actions and identities, unlike the default reactor.
+    pub fn new(replicates: usize, run_limit: usize, sample_size: usize) -> Self {
+        Ensemble {
+            replicates,
+            run_limit,
+            polling_interval: 1000,
+            sample_size,
+            reactor_config: Reactor {
+                discard_copy_actions: false,
+                discard_identity: false,
+                reduction_cutoff: 512,
+                size_cutoff: 1024,
+                ..Reactor::new()
+            },
+            stopping: Stopping::new(),
+        }
+    }
+}
+
+impl XorsetStability {
+    /// Produce a new `XorsetStability` struct with default values. The default pair is only a
+    /// placeholder, and should be replaced by a candidate found with the xorset search.
+    pub fn new() -> Self {
+        XorsetStability {
+            ensemble: Ensemble::new(100, 1000000, 1000),
+            a: String::from("\\x.x"),
+            b: String::from("\\x.\\y.y"),
+            proportions: vec![0.1, 0.25, 0.5, 0.75, 0.9],
+            background_fractions: vec![0.0, 0.5, 0.9],
+            dominance: 0.9,
+        }
+    }
+}
+
+impl XorsetStability {
+    /// Check that every proportion and background fraction is in `[0, 1]`.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let proportions = self.proportions.iter().map(|p| ("proportions", *p));
+        let backgrounds = self
+            .background_fractions
+            .iter()
+            .map(|f| ("background_fractions", *f));
+        for (field, value) in proportions.chain(backgrounds) {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(ConfigError::NotAFraction(field, value));
+            }
         }
+        Ok(())
+    }
+}
+
+impl Default for XorsetStability {
+    fn default() -> Self {
+        XorsetStability::new()
+    }
+}
+
+impl TargetSearch {
+    /// Produce a new `TargetSearch` struct with default values.
+    pub fn new() -> Self {
+        let targets = [
+            ("succ", r"\n.\f.\x. f (n f x)"),
+            ("add", r"\m.\n.\f.\x. m f (n f x)"),
+            ("mul", r"\m.\n.\f. m (n f)"),
+            ("pred", r"\n.\f.\x. n (\g.\h. h (g f)) (\u. x) (\u. u)"),
+            ("is_zero", r"\n. n (\x.\a.\b. b) (\a.\b. a)"),
+            ("pair", r"\a.\b.\f. f a b"),
+            ("fst", r"\p. p (\a.\b. a)"),
+            ("snd", r"\p. p (\a.\b. b)"),
+        ];
+        TargetSearch {
+            ensemble: Ensemble::new(1000, 1000000, 10000),
+            targets: targets
+                .into_iter()
+                .map(|(name, expr)| (name.to_string(), expr.to_string()))
+                .collect(),
+            targets_file: None,
+            match_behavior: false,
+        }
+    }
+}
+
+impl Default for TargetSearch {
+    fn default() -> Self {
+        TargetSearch::new()
+    }
+}
+
+impl ReplicatorSearch {
+    /// Produce a new `ReplicatorSearch` struct with default values.
+    pub fn new() -> Self {
+        ReplicatorSearch {
+            ensemble: Ensemble::new(100, 1000000, 1000),
+            top_k: 20,
+        }
+    }
+}
+
+impl Default for ReplicatorSearch {
+    fn default() -> Self {
+        ReplicatorSearch::new()
+    }
+}
+
+impl Interactions {
+    /// Produce a new `Interactions` struct with default values.
+    pub fn new() -> Self {
+        Interactions {
+            ensemble: Ensemble::new(1, 1000000, 10000),
+            top_k: 20,
+            heatmap: None,
+        }
+    }
+}
+
+impl Default for Interactions {
+    fn default() -> Self {
+        Interactions::new()
+    }
+}
+
+impl AlgebraSearch {
+    /// Produce a new `AlgebraSearch` struct with default values.
+    pub fn new() -> Self {
+        AlgebraSearch {
+            ensemble: Ensemble::new(100, 1000000, 1000),
+            top_k: 10,
+            max_size: 4,
+        }
+    }
+}
+
+impl Default for AlgebraSearch {
+    fn default() -> Self {
+        AlgebraSearch::new()
+    }
+}
+
+impl Families {
+    /// Produce a new `Families` struct with default values.
+    pub fn new() -> Self {
+        Families {
+            ensemble: Ensemble::new(10, 1000000, 1000),
+            top_k: 50,
+            max_distance: 4.0,
+        }
+    }
+}
+
+impl Default for Families {
+    fn default() -> Self {
+        Families::new()
+    }
+}
+
+impl Motifs {
+    /// Produce a new `Motifs` struct with default values.
+    pub fn new() -> Self {
+        Motifs {
+            ensemble: Ensemble::new(10, 1000000, 1000),
+            top_k: 20,
+            min_size: 4,
+        }
+    }
+}
+
+impl Default for Motifs {
+    fn default() -> Self {
+        Motifs::new()
+    }
+}
+
+impl Cycles {
+    /// Produce a new `Cycles` struct with default values.
+    pub fn new() -> Self {
+        Cycles {
+            ensemble: Ensemble::new(100, 1000000, 1000),
+            grain: Grain::Log2,
+            min_repeats: 3,
+        }
+    }
+}
+
+impl Cycles {
+    /// Check that a cycle needs at least one recurrence.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.min_repeats < 2 {
+            return Err(ConfigError::TooSmall("min_repeats", self.min_repeats, 2));
+        }
+        Ok(())
+    }
+}
+
+impl Default for Cycles {
+    fn default() -> Self {
+        Cycles::new()
+    }
+}
+
+impl Perturbation {
+    /// Produce a new `Perturbation` struct with default values.
+    pub fn new() -> Self {
+        Perturbation {
+            ensemble: Ensemble::new(100, 1000000, 1000),
+            warmup: 100000,
+            perturbation: Perturb::Swap,
+            top_k: 10,
+            threshold: 0.5,
+        }
+    }
+}
+
+impl Default for Perturbation {
+    fn default() -> Self {
+        Perturbation::new()
+    }
+}
+
+impl Complexity {
+    /// Produce a new `Complexity` struct with default values.
+    pub fn new() -> Self {
+        Complexity {
+            ensemble: Ensemble::new(10, 1000000, 1000),
+            metrics: vec![
+                Metric::Size,
+                Metric::Abstractions,
+                Metric::Applications,
+                Metric::Redexes,
+                Metric::BinderDepth,
+                Metric::FreeVariables,
+            ],
+        }
+    }
+}
+
+impl Default for Complexity {
+    fn default() -> Self {
+        Complexity::new()
+    }
+}
+
+impl SampleScan {
+    /// Produce a new `SampleScan` struct with default values.
+    pub fn new() -> Self {
+        SampleScan {
+            ensemble: Ensemble::new(1, 1000000, 10000),
+            abundances: Abundances::new(),
+            species_file: None,
+        }
+    }
+}
+
+impl Default for SampleScan {
+    fn default() -> Self {
+        SampleScan::new()
+    }
+}
+
+impl Experiments {
+    /// Produce a new `Experiments` struct with default values.
+    pub fn new() -> Self {
+        Experiments {
+            addition_search: Ensemble::new(1000, 1000000, 10000),
+            xorset_search: Ensemble::new(1000, 10000000, 10000),
+            entropy_test: Ensemble::new(1000, 10000000, 10000),
+            sync_entropy_test: Ensemble::new(100, 100000, 1000),
+            entropy_series: Ensemble::new(1000, 10000000, 10000),
+            sample_simulate: Ensemble::new(1000, 10000000, 10000),
+            sample_scan: SampleScan::new(),
+            xorset_stability: XorsetStability::new(),
+            target_search: TargetSearch::new(),
+            replicator_search: ReplicatorSearch::new(),
+            interactions: Interactions::new(),
+            algebra_search: AlgebraSearch::new(),
+            family_tracking: Families::new(),
+            motif_series: Motifs::new(),
+            cycle_detection: Cycles::new(),
+            perturbation: Perturbation::new(),
+            complexity_histograms: Complexity::new(),
+            compression_series: Ensemble::new(10, 1000000, 10000),
+        }
+    }
+}
+
+impl Experiments {
+    /// Check that no stopping criterion is set in a section whose experiment ignores it.
+    fn validate_unused_stopping(&self) -> Result<(), ConfigError> {
+        let sections = [
+            ("addition_search", &self.addition_search),
+            ("xorset_search", &self.xorset_search),
+            ("entropy_test", &self.entropy_test),
+            ("sync_entropy_test", &self.sync_entropy_test),
+            ("sample_scan", &self.sample_scan.ensemble),
+            ("xorset_stability", &self.xorset_stability.ensemble),
+            ("target_search", &self.target_search.ensemble),
+            ("replicator_search", &self.replicator_search.ensemble),
+            ("interactions", &self.interactions.ensemble),
+            ("algebra_search", &self.algebra_search.ensemble),
+            ("family_tracking", &self.family_tracking.ensemble),
+            ("motif_series", &self.motif_series.ensemble),
+            ("cycle_detection", &self.cycle_detection.ensemble),
+            ("perturbation", &self.perturbation.ensemble),
+            (
+                "complexity_histograms",
+                &self.complexity_histograms.ensemble,
+            ),
+            ("compression_series", &self.compression_series),
+        ];
+        for (section, ensemble) in sections {
+            if ensemble.stopping.is_set() {
+                return Err(ConfigError::UnusedStopping(section));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for Experiments {
+    fn default() -> Self {
+        Experiments::new()
     }
 }
 
@@ -201,6 +1009,9 @@ impl Config {
             sample_size: 1000,
             polling_interval: None,
             verbose_logging: false,
+            probe_config: Probes::new(),
+            abundance_config: Abundances::new(),
+            experiments: Experiments::new(),
         }
     }
 
@@ -219,12 +1030,60 @@ impl Config {
     pub fn set_verbose_logging(&mut self, logging: bool) {
         self.verbose_logging = logging;
     }
+
+    /// Check the values that deserialization alone does not constrain.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.experiments.xorset_stability.validate()?;
+        self.experiments.sample_simulate.stopping.validate()?;
+        self.experiments.entropy_series.stopping.validate()?;
+        self.experiments.cycle_detection.validate()?;
+        self.experiments.validate_unused_stopping()
+    }
+
+    /// Replace the seeds of both the reactor and the expression generator.
+    pub fn set_seeds(&mut self, reactor_seed: ConfigSeed, generator_seed: ConfigSeed) {
+        self.reactor_config.seed = reactor_seed;
+        self.generator_config.set_seed(generator_seed);
+    }
 }
 
+/// A configuration value outside of the range in which it is meaningful.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    /// A fraction outside of `[0, 1]`, with the name of its field
+    NotAFraction(&'static str, f64),
+
+    /// A count below its smallest meaningful value, with the name of its field and that value
+    TooSmall(&'static str, usize, usize),
+
+    /// A stopping criterion set in the section of an experiment that ignores it
+    UnusedStopping(&'static str),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::NotAFraction(field, value) => {
+                write!(f, "{} must be between 0 and 1, got {}", field, value)
+            }
+            ConfigError::TooSmall(field, value, min) => {
+                write!(f, "{} must be at least {}, got {}", field, min, value)
+            }
+            ConfigError::UnusedStopping(section) => write!(
+                f,
+                "stopping is set in {}, but only sample_simulate and entropy_series use it",
+                section
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
 /// Represents a seed for serde RNGs in the configuration file. Mostly here because we want
 /// to ser/de to/from a hex string.
 #[warn(missing_docs)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ConfigSeed(Option<[u8; 32]>);
 
 impl ConfigSeed {
@@ -268,9 +1127,10 @@ impl<'de> Deserialize<'de> for ConfigSeed {
     where
         D: serde::Deserializer<'de>,
     {
-        let seed_string: Option<&str> = serde::de::Deserialize::deserialize(deserializer)?;
+        // Owned rather than borrowed, so that seeds can also be read from a `serde_json::Value`
+        let seed_string: Option<String> = serde::de::Deserialize::deserialize(deserializer)?;
         Ok(if let Some(s) = seed_string {
-            let hexvec = decode_hex(s).unwrap();
+            let hexvec = decode_hex(&s).unwrap();
             ConfigSeed::new(hexvec.try_into().unwrap())
         } else {
             ConfigSeed::blank()
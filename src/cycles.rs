@@ -0,0 +1,156 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::Observable;
+use crate::config;
+use crate::novelty::species_hash;
+use crate::soup::{Soup, Tape};
+
+/// How finely a composition fingerprint distinguishes species counts. Coarser grains make
+/// approximate recurrences of the soup show up as exact recurrences of its fingerprint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+pub enum Grain {
+    /// Exact number of copies of each species
+    Exact,
+
+    /// Number of copies of each species, rounded down to a power of two
+    Log2,
+
+    /// Only which species are present
+    Presence,
+}
+
+/// A recurrence of the soup composition that repeats until the end of a trajectory.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Cycle {
+    /// Number of polls between recurrences. A period of 1 means the soup is frozen.
+    pub period: usize,
+
+    /// First poll of the periodic part of the trajectory
+    pub start: usize,
+
+    /// Number of complete periods from `start` to the end of the trajectory
+    pub repeats: usize,
+
+    /// Difference between the largest and smallest value of the observable from `start` to the
+    /// end of the trajectory
+    #[serde(with = "crate::ensemble::nan_as_null")]
+    pub amplitude: f32,
+}
+
+impl Grain {
+    fn coarsen(&self, count: u32) -> u32 {
+        match self {
+            Grain::Exact => count,
+            Grain::Log2 => count.ilog2(),
+            Grain::Presence => 0,
+        }
+    }
+}
+
+/// Find the shortest period with which the end of `fingerprints` repeats at least
+/// `min_repeats` times, and measure the amplitude of `values` over the periodic part. `values`
+/// must have the same length as `fingerprints`. Returns `None` if the trajectory does not end in
+/// a cycle. A `min_repeats` below 2 is taken as 2, where a single recurrence of the last
+/// fingerprints already counts as a cycle.
+pub fn detect_cycle(fingerprints: &[u64], values: &[f32], min_repeats: usize) -> Option<Cycle> {
+    let n = fingerprints.len();
+    let min_repeats = min_repeats.max(2);
+    (1..=n / min_repeats).find_map(|period| {
+        // Number of polls, counting back from the last one, that recur `period` polls later
+        let matching = (period..n)
+            .rev()
+            .take_while(|t| fingerprints[*t] == fingerprints[t - period])
+            .count();
+        if matching < (min_repeats - 1) * period {
+            return None;
+        }
+        let start = n - matching - period;
+        let tail = &values[start..];
+        let max = tail.iter().copied().fold(f32::MIN, f32::max);
+        let min = tail.iter().copied().fold(f32::MAX, f32::min);
+        Some(Cycle {
+            period,
+            start,
+            repeats: (n - start) / period,
+            amplitude: max - min,
+        })
+    })
+}
+
+impl Soup {
+    /// Order independent fingerprint of the composition of the soup, at the given `grain`. Two
+    /// soups with the same species and (coarsened) counts have the same fingerprint, however
+    /// their expressions are ordered.
+    pub fn fingerprint(&self, grain: Grain) -> u64 {
+        self.expression_counts()
+            .iter()
+            .map(|(expr, count)| {
+                let mut hasher = DefaultHasher::new();
+                (species_hash(expr), grain.coarsen(*count)).hash(&mut hasher);
+                hasher.finish()
+            })
+            .fold(0, u64::wrapping_add)
+    }
+}
+
+impl Tape {
+    /// Fingerprint of every recorded snapshot of the soup, at the given `grain`.
+    pub fn fingerprints(&self, grain: Grain) -> Vec<u64> {
+        self.history().map(|s| s.fingerprint(grain)).collect()
+    }
+
+    /// Look for a cycle that repeats at least `min_repeats` times at the end of the recorded
+    /// snapshots, see `detect_cycle`. The amplitude is measured on `observable`, with behavior
+    /// fingerprinted on `probes`.
+    pub fn detect_cycle(
+        &self,
+        grain: Grain,
+        min_repeats: usize,
+        observable: Observable,
+        probes: &config::Probes,
+    ) -> Option<Cycle> {
+        let values = self
+            .history()
+            .map(|s| observable.measure(s, probes))
+            .collect::<Vec<_>>();
+        detect_cycle(&self.fingerprints(grain), &values, min_repeats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn period_two_trajectory() {
+        let fingerprints = [5, 1, 2, 1, 2, 1, 2];
+        let values = [9.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0];
+        let cycle = detect_cycle(&fingerprints, &values, 3).unwrap();
+        assert_eq!(cycle.period, 2);
+        assert_eq!(cycle.start, 1);
+        assert_eq!(cycle.repeats, 3);
+        assert_eq!(cycle.amplitude, 1.0);
+        assert_eq!(detect_cycle(&fingerprints, &values, 4), None);
+    }
+
+    #[test]
+    fn frozen_trajectory() {
+        let cycle = detect_cycle(&[7, 7, 7], &[2.0, 2.0, 2.0], 3).unwrap();
+        assert_eq!((cycle.period, cycle.start, cycle.repeats), (1, 0, 3));
+        assert_eq!(cycle.amplitude, 0.0);
+    }
+
+    #[test]
+    fn single_recurrence() {
+        let fingerprints = [1, 2, 3, 2, 3];
+        let values = [0.0; 5];
+        // Two periods of `2, 3` only count as a cycle when two repeats are enough
+        let cycle = detect_cycle(&fingerprints, &values, 2).unwrap();
+        assert_eq!((cycle.period, cycle.start, cycle.repeats), (2, 1, 2));
+        assert_eq!(detect_cycle(&fingerprints, &values, 3), None);
+        assert_eq!(detect_cycle(&[], &[], 3), None);
+    }
+}
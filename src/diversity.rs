@@ -0,0 +1,100 @@
+use crate::soup::Soup;
+
+/// Unit of information for Shannon entropy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Base {
+    /// Logarithm base 2
+    Bits,
+
+    /// Natural logarithm
+    Nats,
+
+    /// Logarithm base 10
+    Dits,
+}
+
+impl Base {
+    pub(crate) fn log(&self, x: f64) -> f64 {
+        match self {
+            Base::Bits => x.log2(),
+            Base::Nats => x.ln(),
+            Base::Dits => x.log10(),
+        }
+    }
+}
+
+/// Relative abundance of each species with a nonzero count.
+fn proportions(counts: &[u32]) -> impl Iterator<Item = f64> + '_ {
+    let total = counts.iter().map(|c| *c as f64).sum::<f64>();
+    counts
+        .iter()
+        .filter(|c| **c > 0)
+        .map(move |c| *c as f64 / total)
+}
+
+/// Number of species with a nonzero count.
+pub fn richness(counts: &[u32]) -> usize {
+    counts.iter().filter(|c| **c > 0).count()
+}
+
+/// Shannon entropy of the abundance distribution, in the unit given by `base`.
+pub fn shannon(counts: &[u32], base: Base) -> f64 {
+    -proportions(counts).map(|p| p * base.log(p)).sum::<f64>()
+}
+
+/// Simpson's index, the probability that two individuals drawn with replacement belong to the
+/// same species. Lower values mean a more diverse soup.
+pub fn simpson(counts: &[u32]) -> f64 {
+    proportions(counts).map(|p| p * p).sum()
+}
+
+/// Gini-Simpson index, the probability that two individuals drawn with replacement belong to
+/// different species.
+pub fn gini_simpson(counts: &[u32]) -> f64 {
+    1.0 - simpson(counts)
+}
+
+/// Hill number of order `q`, the effective number of species. Order 0 is the richness, order 1
+/// the exponential of the Shannon entropy in nats, order 2 the inverse of Simpson's index, and
+/// order infinity the inverse of the Berger-Parker index. Order minus infinity is the inverse of
+/// the relative abundance of the rarest species.
+pub fn hill(counts: &[u32], q: f64) -> f64 {
+    if richness(counts) == 0 {
+        return 0.0;
+    }
+    if q == 1.0 {
+        return shannon(counts, Base::Nats).exp();
+    }
+    if q == f64::INFINITY {
+        return 1.0 / berger_parker(counts);
+    }
+    if q == f64::NEG_INFINITY {
+        return 1.0 / proportions(counts).fold(1.0, f64::min);
+    }
+    proportions(counts)
+        .map(|p| p.powf(q))
+        .sum::<f64>()
+        .powf(1.0 / (1.0 - q))
+}
+
+/// Pielou's evenness, the Shannon entropy divided by its largest possible value for the same
+/// richness. Soups with fewer than two species are perfectly even.
+pub fn evenness(counts: &[u32]) -> f64 {
+    let s = richness(counts);
+    if s < 2 {
+        return 1.0;
+    }
+    shannon(counts, Base::Nats) / (s as f64).ln()
+}
+
+/// Berger-Parker index, the relative abundance of the most abundant species.
+pub fn berger_parker(counts: &[u32]) -> f64 {
+    proportions(counts).fold(0.0, f64::max)
+}
+
+impl Soup {
+    /// Number of copies of each distinct expression in the soup, in no particular order.
+    pub fn abundances(&self) -> Vec<u32> {
+        self.expression_counts().into_values().collect()
+    }
+}
@@ -0,0 +1,394 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use lambda_calculus::Term;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::config::ConfigSeed;
+
+/// A single member of an ensemble. Each replicate carries its own seed, so the result of a
+/// replicate does not depend on which worker ran it or in which order.
+#[derive(Debug, Clone, Copy)]
+pub struct Replicate {
+    /// Index of this replicate within the ensemble.
+    pub id: usize,
+    seed: [u8; 32],
+}
+
+/// Runs a number of independent simulations on a fixed pool of worker threads. Simulations
+/// are CPU-bound, so each worker runs one replicate at a time to completion.
+pub struct Ensemble {
+    n_workers: usize,
+    seed: [u8; 32],
+    results_dir: Option<Arc<ResultsDir>>,
+}
+
+/// Results of an ensemble run. Replicates that were already stored in the results directory
+/// are yielded first, followed by the remaining replicates in the order in which they finish.
+pub struct Results<T> {
+    stored: std::vec::IntoIter<(Replicate, T)>,
+    receiver: Receiver<(Replicate, T)>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+/// Describes an ensemble run stored in a results directory. A run can only be resumed with the
+/// same manifest it was started with. Flags that only change how results are reported, like
+/// `--format` and `--summary`, are left out: stored replicates do not depend on them, so a
+/// finished run can be reported again in another form.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Manifest {
+    /// Identifier of the run. All replicate seeds are derived from it.
+    pub run_id: String,
+
+    /// Name of the experiment that produced the run
+    pub experiment: String,
+
+    /// Full configuration the run was started with
+    pub config: serde_json::Value,
+
+    /// Observable polled by the run, see `--observable`
+    #[serde(default)]
+    pub observable: String,
+
+    /// Hash of the expressions read from `stdin`, for experiments that seed their soups from it
+    #[serde(default)]
+    pub inputs: Option<u64>,
+}
+
+/// A directory holding the output of an ensemble run: a `manifest.json`, and one file for each
+/// finished replicate.
+#[derive(Debug)]
+pub struct ResultsDir {
+    path: PathBuf,
+}
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// FNV-1a hash of `bytes`, which unlike the std hashers is guaranteed to be stable across
+/// releases.
+fn stable_hash(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// Derive a base seed deterministically from a run identifier.
+pub fn seed_from_run_id(run_id: &str) -> [u8; 32] {
+    ChaCha8Rng::seed_from_u64(stable_hash(run_id.bytes())).gen()
+}
+
+/// Hash of a sample of expressions, stable across runs, to record in a `Manifest`.
+pub fn inputs_hash(inputs: &[Term]) -> u64 {
+    stable_hash(inputs.iter().flat_map(|e| format!("{}\n", e).into_bytes()))
+}
+
+/// Serde adapter for `f32` fields of stored results. JSON has no number for NaN or infinity, so
+/// non-finite values are stored as `null`, and `null` is read back as NaN. Use it with
+/// `#[serde(with = "crate::ensemble::nan_as_null")]`, or `nan_as_null::vec` for a `Vec<f32>`.
+pub mod nan_as_null {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(value: &f32, serializer: S) -> Result<S::Ok, S::Error> {
+        value.is_finite().then_some(*value).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+        Ok(Option::<f32>::deserialize(deserializer)?.unwrap_or(f32::NAN))
+    }
+
+    /// The same adapter for each value of a `Vec<f32>`.
+    pub mod vec {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(values: &[f32], serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(values.iter().map(|x| x.is_finite().then_some(*x)))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Vec<f32>, D::Error> {
+            let values = Vec::<Option<f32>>::deserialize(deserializer)?;
+            Ok(values.into_iter().map(|x| x.unwrap_or(f32::NAN)).collect())
+        }
+    }
+}
+
+impl ResultsDir {
+    /// Read the manifest stored in the results directory at `path`.
+    pub fn read_manifest(path: impl AsRef<Path>) -> io::Result<Manifest> {
+        let contents = fs::read_to_string(path.as_ref().join(MANIFEST_FILE))?;
+        serde_json::from_str(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Open the results directory at `path`, creating it if necessary. If the directory
+    /// already holds a run, its manifest must match `manifest`.
+    pub fn open(path: impl AsRef<Path>, manifest: &Manifest) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if path.join(MANIFEST_FILE).exists() {
+            let existing = ResultsDir::read_manifest(&path)?;
+            if existing != *manifest {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "{} holds run {} of experiment {} with a different configuration, \
+                         observable or input",
+                        path.display(),
+                        existing.run_id,
+                        existing.experiment
+                    ),
+                ));
+            }
+        } else {
+            fs::create_dir_all(&path)?;
+            let contents = serde_json::to_string_pretty(manifest)?;
+            fs::write(path.join(MANIFEST_FILE), contents)?;
+        }
+        Ok(ResultsDir { path })
+    }
+
+    fn replicate_path(&self, id: usize) -> PathBuf {
+        self.path.join(format!("replicate-{:06}.json", id))
+    }
+
+    /// Whether the replicate with index `id` has finished and been stored.
+    pub fn is_complete(&self, id: usize) -> bool {
+        self.replicate_path(id).exists()
+    }
+
+    /// Store the result of `replicate`. The file is written under a temporary name first, so an
+    /// interrupted write never leaves a replicate that looks complete.
+    pub fn write_replicate<T: Serialize>(&self, replicate: &Replicate, data: &T) -> io::Result<()> {
+        let path = self.replicate_path(replicate.id);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(data)?)?;
+        fs::rename(tmp, path)
+    }
+
+    /// Load the stored result of the replicate with index `id`.
+    pub fn read_replicate<T: DeserializeOwned>(&self, id: usize) -> io::Result<T> {
+        let contents = fs::read_to_string(self.replicate_path(id))?;
+        serde_json::from_str(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl Replicate {
+    fn derive_seed(&self, stream: u8) -> [u8; 32] {
+        let mut seed = self.seed;
+        seed[31] ^= stream;
+        seed
+    }
+
+    /// Seed for the reactor of this replicate.
+    pub fn reactor_seed(&self) -> ConfigSeed {
+        ConfigSeed::new(self.derive_seed(0))
+    }
+
+    /// Seed for the expression generator of this replicate. Distinct from the reactor seed, so
+    /// that the generator and the reactor do not draw from the same random stream.
+    pub fn generator_seed(&self) -> ConfigSeed {
+        ConfigSeed::new(self.derive_seed(1))
+    }
+
+    /// Seed for perturbing the soup of this replicate, distinct from the reactor and generator
+    /// seeds.
+    pub fn perturbation_seed(&self) -> ConfigSeed {
+        ConfigSeed::new(self.derive_seed(2))
+    }
+}
+
+impl Ensemble {
+    /// Create an ensemble that runs on `n_workers` threads. If `n_workers` is zero, use one
+    /// worker per available core.
+    pub fn new(n_workers: usize) -> Self {
+        let n_workers = if n_workers == 0 {
+            thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        } else {
+            n_workers
+        };
+        Ensemble {
+            n_workers,
+            seed: [0; 32],
+            results_dir: None,
+        }
+    }
+
+    /// Derive all replicate seeds from `run_id`, so that a replicate can be rerun on its own.
+    pub fn with_run_id(mut self, run_id: &str) -> Self {
+        self.seed = seed_from_run_id(run_id);
+        self
+    }
+
+    /// Store the result of each replicate in `dir`. Replicates that are already stored there are
+    /// loaded instead of being run again.
+    pub fn with_results_dir(mut self, dir: ResultsDir) -> Self {
+        self.results_dir = Some(Arc::new(dir));
+        self
+    }
+
+    /// Get the replicate with index `id`.
+    pub fn replicate(&self, id: usize) -> Replicate {
+        let mut seed = self.seed;
+        for (s, b) in seed.iter_mut().zip(id.to_le_bytes()) {
+            *s ^= b;
+        }
+        Replicate { id, seed }
+    }
+
+    /// Run `job` once for each of `n` replicates. Results are streamed back as soon as each
+    /// replicate finishes.
+    ///
+    /// Fails when a stored replicate cannot be read.
+    ///
+    /// SAFETY: `panic!`s when a result cannot be stored.
+    pub fn run<F, T>(&self, n: usize, job: F) -> io::Result<Results<T>>
+    where
+        F: Fn(Replicate) -> T + Send + Sync + 'static,
+        T: Serialize + DeserializeOwned + Send + 'static,
+    {
+        let (done, pending): (Vec<_>, Vec<_>) = (0..n).map(|i| self.replicate(i)).partition(|r| {
+            self.results_dir
+                .as_ref()
+                .is_some_and(|d| d.is_complete(r.id))
+        });
+
+        let stored = done
+            .into_iter()
+            .map(|r| {
+                let dir = self.results_dir.as_ref().unwrap();
+                let data = dir.read_replicate(r.id).map_err(|e| {
+                    io::Error::new(e.kind(), format!("cannot read replicate {}: {}", r.id, e))
+                })?;
+                Ok((r, data))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let n_pending = pending.len();
+        let replicates = Arc::new(pending);
+        let next = Arc::new(AtomicUsize::new(0));
+        let job = Arc::new(job);
+        let (sender, receiver) = channel();
+
+        let workers = (0..self.n_workers.min(n_pending))
+            .map(|_| {
+                let replicates = Arc::clone(&replicates);
+                let next = Arc::clone(&next);
+                let job = Arc::clone(&job);
+                let results_dir = self.results_dir.clone();
+                let sender = sender.clone();
+                thread::spawn(move || loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(replicate) = replicates.get(i) else {
+                        break;
+                    };
+                    let result = job(*replicate);
+                    if let Some(dir) = &results_dir {
+                        dir.write_replicate(replicate, &result).unwrap_or_else(|e| {
+                            panic!("cannot store replicate {}: {}", replicate.id, e)
+                        });
+                    }
+                    if sender.send((*replicate, result)).is_err() {
+                        break;
+                    }
+                })
+            })
+            .collect();
+
+        Ok(Results {
+            stored: stored.into_iter(),
+            receiver,
+            workers,
+        })
+    }
+}
+
+impl<T> Iterator for Results<T> {
+    type Item = (Replicate, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(item) = self.stored.next() {
+            return Some(item);
+        }
+        match self.receiver.recv() {
+            Ok(item) => Some(item),
+            Err(_) => {
+                // All senders are gone, so every worker has exited. Surface any worker panic
+                // instead of silently returning a truncated ensemble.
+                for worker in self.workers.drain(..) {
+                    if let Err(e) = worker.join() {
+                        std::panic::resume_unwind(e);
+                    }
+                }
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct Stored {
+        #[serde(with = "nan_as_null")]
+        value: f32,
+        #[serde(with = "nan_as_null::vec")]
+        series: Vec<f32>,
+    }
+
+    fn temp_results_dir(run_id: &str) -> (PathBuf, ResultsDir) {
+        let name = format!("alchemy-{}-{}", run_id, std::process::id());
+        let path = std::env::temp_dir().join(name);
+        let manifest = Manifest {
+            run_id: run_id.to_string(),
+            experiment: "test".to_string(),
+            config: serde_json::Value::Null,
+            observable: String::new(),
+            inputs: None,
+        };
+        let dir = ResultsDir::open(&path, &manifest).unwrap();
+        (path, dir)
+    }
+
+    #[test]
+    fn nan_round_trip() {
+        let (path, dir) = temp_results_dir("nan");
+        let ensemble = Ensemble::new(1).with_run_id("nan");
+        let stored = Stored {
+            value: f32::NAN,
+            series: vec![1.0, f32::NAN, f32::INFINITY],
+        };
+        dir.write_replicate(&ensemble.replicate(0), &stored)
+            .unwrap();
+
+        let read: Stored = dir.read_replicate(0).unwrap();
+        fs::remove_dir_all(&path).unwrap();
+        assert!(read.value.is_nan());
+        assert_eq!(read.series[0], 1.0);
+        // Infinity is not a JSON number either, so it is read back as NaN
+        assert!(read.series[1].is_nan() && read.series[2].is_nan());
+    }
+
+    #[test]
+    fn unreadable_replicate_is_an_error() {
+        let (path, dir) = temp_results_dir("bad");
+        fs::write(dir.replicate_path(0), "[null]").unwrap();
+
+        let ensemble = Ensemble::new(1).with_results_dir(dir);
+        let result = ensemble.run(1, |_| vec![0f32]);
+        fs::remove_dir_all(&path).unwrap();
+        assert_eq!(result.err().unwrap().kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -1,146 +1,622 @@
-use std::collections::HashMap;
+use std::collections::HashSet;
 use std::error::Error;
+use std::fs::read_to_string;
+use std::io::{self, Write};
+use std::sync::Arc;
 
-use async_std::task::spawn;
 use clap::error::Result;
-use futures::{stream::FuturesUnordered, StreamExt};
 use lambda_calculus::{app, parse, term::Notation::Classic, Term};
 use plotters::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    abundance::{AbundanceRecorder, AbundanceTracker, Abundances},
+    algebra::find_structures,
+    analysis::Observable,
+    behavior::Prober,
+    classifier::Classifier,
+    complexity::{Distribution, Metric},
+    compression::{CompressionPoint, CompressionTracker},
     config,
-    generators::BTreeGen,
-    read_inputs,
+    cycles::{detect_cycle, Cycle},
+    diversity::Base,
+    ensemble::{Ensemble, Replicate},
+    families::track_families,
+    generators::generate_from_config,
+    interaction::{InteractionMatrix, Product},
     soup::{reduce_with_limit, Soup},
+    statistics::{self, PollSummary, SeriesAggregator, SummaryOptions},
+    stopping::Stop,
 };
 
-async fn simulate_additive_murder(
-    sample: impl Iterator<Item = Term>,
-    id: usize,
-    run_length: usize,
-    polling_interval: usize,
-) -> (usize, Vec<usize>) {
-    let mut soup = Soup::from_config(&config::Reactor {
-        rules: vec![String::from("\\x.\\y.x y")],
-        discard_copy_actions: false,
-        discard_identity: false,
-        discard_free_variable_expressions: true,
-        maintain_constant_population_size: true,
-        discard_parents: false,
-        reduction_cutoff: 512,
-        size_cutoff: 1024,
-        seed: config::ConfigSeed::new([0; 32]),
+/// An experiment that can be listed, described and run by name. Each experiment reads its
+/// parameters from its own section of `config::Experiments`, and returns structured results.
+pub trait Experiment: Sync {
+    /// Name used to select the experiment from the command line
+    fn name(&self) -> &'static str;
+
+    /// One-line description of the experiment
+    fn description(&self) -> &'static str;
+
+    /// The ensemble options of this experiment's configuration section
+    fn section<'a>(&self, cfg: &'a config::Experiments) -> &'a config::Ensemble;
+
+    /// Mutable access to the ensemble options, so that command line flags can override them
+    fn section_mut<'a>(&self, cfg: &'a mut config::Experiments) -> &'a mut config::Ensemble;
+
+    /// Whether the experiment seeds its soups from the expressions read from `stdin`, see
+    /// `Context::inputs`
+    fn reads_stdin(&self) -> bool {
+        false
+    }
+
+    /// Whether the experiment writes the abundances of each soup at each poll when
+    /// `Context::abundances` is set
+    fn records_abundances(&self) -> bool {
+        false
+    }
+
+    /// Parameters of this experiment, with their values in `cfg`
+    fn parameters(&self, cfg: &config::Config) -> Vec<Parameter> {
+        ensemble_parameters(self.section(&cfg.experiments))
+    }
+
+    /// Run the experiment. Fails when the results of a resumed run cannot be read.
+    fn run(&self, ctx: &Context) -> io::Result<Box<dyn Report>>;
+}
+
+/// A single parameter of an experiment
+#[derive(Debug, Clone, Serialize)]
+pub struct Parameter {
+    /// Name of the field in the experiment's configuration section
+    pub name: &'static str,
+
+    /// What the parameter controls
+    pub description: &'static str,
+
+    /// Current value of the parameter
+    pub value: serde_json::Value,
+}
+
+/// Everything an experiment needs to run.
+pub struct Context<'a> {
+    /// Ensemble that runs the replicates of the experiment
+    pub ensemble: &'a Ensemble,
+
+    /// Global configuration, including the experiment's own section
+    pub config: &'a config::Config,
+
+    /// Quantity polled by experiments that produce a series for each soup
+    pub observable: Observable,
+
+    /// When set, experiments that produce a series for each soup summarize the series across
+    /// the ensemble instead of reporting each soup.
+    pub summary: bool,
+
+    /// Expressions read from `stdin`, for experiments that seed their soups from them
+    pub inputs: &'a [Term],
+
+    /// Where to write the abundances of each soup, for experiments that record them
+    pub abundances: Option<&'a AbundanceRecorder>,
+}
+
+/// Structured results of an experiment.
+pub trait Report {
+    /// Write the results as CSV, starting with a header row.
+    fn write_csv(&self, out: &mut dyn Write) -> io::Result<()>;
+
+    /// Write the results as a single JSON document.
+    fn write_json(&self, out: &mut dyn Write) -> io::Result<()>;
+}
+
+/// All available experiments.
+pub fn registry() -> &'static [&'static dyn Experiment] {
+    &[
+        &AdditionSearch,
+        &XorsetSearch,
+        &XorsetStability,
+        &TargetSearch,
+        &ReplicatorSearch,
+        &Interactions,
+        &AlgebraSearch,
+        &FamilyTracking,
+        &MotifSeries,
+        &CycleDetection,
+        &PerturbationSensitivity,
+        &ComplexityHistograms,
+        &CompressionSeries,
+        &EntropyTest,
+        &SyncEntropyTest,
+        &EntropySeries,
+        &SampleSimulate,
+        &SampleScan,
+    ]
+}
+
+/// Find the experiment called `name`.
+pub fn find(name: &str) -> Option<&'static dyn Experiment> {
+    registry().iter().find(|e| e.name() == name).copied()
+}
+
+/// Write `report` to `out` in the given `format`.
+pub fn write_report(
+    report: &dyn Report,
+    format: statistics::Format,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    match format {
+        statistics::Format::Csv => report.write_csv(out),
+        statistics::Format::Json => report.write_json(out),
+    }
+}
+
+fn write_json(value: &impl Serialize, out: &mut dyn Write) -> io::Result<()> {
+    serde_json::to_writer(&mut *out, value)?;
+    writeln!(out)
+}
+
+/// Parameters shared by all experiments that run an ensemble of soups.
+pub fn ensemble_parameters(cfg: &config::Ensemble) -> Vec<Parameter> {
+    let value = |v| serde_json::to_value(v).unwrap();
+    vec![
+        Parameter {
+            name: "replicates",
+            description: "Number of soups in the ensemble",
+            value: value(&cfg.replicates),
+        },
+        Parameter {
+            name: "run_limit",
+            description: "Number of reactions to run in each soup",
+            value: value(&cfg.run_limit),
+        },
+        Parameter {
+            name: "polling_interval",
+            description: "Poll each soup every `polling_interval` reactions",
+            value: value(&cfg.polling_interval),
+        },
+        Parameter {
+            name: "sample_size",
+            description: "Number of expressions each soup starts with",
+            value: value(&cfg.sample_size),
+        },
+        Parameter {
+            name: "reactor_config",
+            description: "Configuration of the reactor of each soup",
+            value: serde_json::to_value(&cfg.reactor_config).unwrap(),
+        },
+    ]
+}
+
+/// Parameters of experiments that poll a series and can stop each soup early.
+fn stopping_parameters(cfg: &config::Ensemble) -> Vec<Parameter> {
+    let mut parameters = ensemble_parameters(cfg);
+    parameters.push(Parameter {
+        name: "stopping",
+        description: "Criteria for stopping each soup early",
+        value: serde_json::to_value(&cfg.stopping).unwrap(),
     });
+    parameters
+}
+
+/// Produce a `sample_size` sample of expressions from the generator described by `generator`,
+/// seeded for `replicate`.
+fn generate_sample(
+    generator: &config::Generator,
+    replicate: &Replicate,
+    sample_size: usize,
+) -> Vec<Term> {
+    let mut generator = generator.clone();
+    generator.set_seed(replicate.generator_seed());
+    generate_from_config(&generator, sample_size)
+}
+
+/// Produce a soup for `replicate`, configured by `cfg` and seeded with `sample`.
+fn seed_soup(
+    cfg: &config::Ensemble,
+    replicate: &Replicate,
+    sample: impl IntoIterator<Item = Term>,
+) -> Soup {
+    let mut reactor_config = cfg.reactor_config.clone();
+    reactor_config.seed = replicate.reactor_seed();
+    let mut soup = Soup::from_config(&reactor_config);
     soup.perturb(sample);
-    let add = parse(r"\m.\n. m ((\m.\n. m (\n.\x.\y. x (n x y)) n) n) (\x.\y.y)", Classic).unwrap();
-    let check_series =
-        soup.simulate_and_poll_with_killer(run_length, polling_interval, false, |s| {
-            (s.collisions(), s.expressions().any(|e| e.is_isomorphic_to(&add)))
-        });
-    (id, check_series)
-}
-
-pub async fn look_for_add() {
-    let mut futures = FuturesUnordered::new();
-    let run_length = 1000000;
-    let polling_interval = 1000;
-    let sample = read_inputs().collect::<Vec<Term>>();
-    for i in 0..1000 {
-        futures.push(spawn(simulate_additive_murder(
-            sample.clone().into_iter().cycle().take(10000),
-            i,
-            run_length,
-            polling_interval,
-        )));
-    }
-
-    print!("Soup, ");
-    println!();
-    while let Some((id, series)) = futures.next().await {
-        print!("{}, ", id);
-        for i in series {
-            print!("{:?}, ", i)
-        }
-        println!();
-    }
-}
-
-pub fn one_sample_with_dist() {
-    let run_length = 1000000;
-    let polling_interval = 1000;
-    let polls = run_length / polling_interval;
-    let sample = read_inputs().collect::<Vec<Term>>();
-
-    let mut soup = Soup::from_config(&config::Reactor {
-        rules: vec![String::from("\\x.\\y.x y")],
-        discard_copy_actions: false,
-        discard_identity: false,
-        discard_free_variable_expressions: true,
-        maintain_constant_population_size: true,
-        discard_parents: false,
-        reduction_cutoff: 512,
-        size_cutoff: 1024,
-        seed: config::ConfigSeed::new([0; 32]),
-    });
-    soup.perturb(sample.into_iter().cycle().take(10000));
-    let counts = soup.simulate_and_poll(run_length, polling_interval, false, |s| {
-        s.expression_counts()
-    });
+    soup
+}
 
-    let mut map = HashMap::<Term, Vec<u32>>::new();
-    for (i, count) in counts.iter().enumerate() {
-        for (term, val) in count.iter() {
-            map.entry(term.clone())
-                .or_insert(vec![0; i.try_into().unwrap()])
-                .push(*val);
+/// Polled series of each soup in an ensemble
+#[derive(Debug, Serialize)]
+pub struct SeriesReport {
+    /// Number of polls in a complete run
+    pub polls: usize,
+
+    /// Series of each soup, by replicate, with why and when the soup stopped
+    pub series: Vec<(usize, Stop, Vec<f32>)>,
+}
+
+/// Summary of the polled series of an ensemble, at each poll
+#[derive(Debug, Serialize)]
+pub struct SummaryReport {
+    #[serde(skip)]
+    options: SummaryOptions,
+
+    pub summaries: Vec<PollSummary>,
+}
+
+impl Report for SeriesReport {
+    fn write_csv(&self, out: &mut dyn Write) -> io::Result<()> {
+        write!(out, "soup,stopped_by,stopped_at")?;
+        for i in 0..self.polls {
+            write!(out, ",{}", i)?;
         }
-        for (term, vals) in map.iter_mut() {
-            if !count.contains_key(term) {
-                vals.push(0);
+        writeln!(out)?;
+        for (id, stop, data) in &self.series {
+            write!(out, "{},{},{}", id, stop.reason, stop.reactions)?;
+            for x in data {
+                write!(out, ",{}", x)?;
             }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    fn write_json(&self, out: &mut dyn Write) -> io::Result<()> {
+        write_json(self, out)
+    }
+}
+
+impl Report for SummaryReport {
+    fn write_csv(&self, mut out: &mut dyn Write) -> io::Result<()> {
+        let format = statistics::Format::Csv;
+        statistics::write_summaries(&mut out, &self.summaries, &self.options, format)
+    }
+
+    fn write_json(&self, out: &mut dyn Write) -> io::Result<()> {
+        write_json(&self.summaries, out)
+    }
+}
+
+/// Polled series of one soup, with why and when the soup stopped
+#[derive(Debug, Serialize, Deserialize)]
+struct PolledSeries {
+    #[serde(with = "crate::ensemble::nan_as_null::vec")]
+    data: Vec<f32>,
+    stop: Stop,
+}
+
+/// Collect polled series into a report. If `summary` is set, summarize the series across the
+/// ensemble instead.
+fn series_report(
+    results: impl Iterator<Item = (Replicate, PolledSeries)>,
+    polls: usize,
+    summary: bool,
+) -> Box<dyn Report> {
+    if summary {
+        let mut aggregator = SeriesAggregator::new();
+        for (_, PolledSeries { data, .. }) in results {
+            aggregator.push(data);
         }
+        let options = SummaryOptions::new();
+        let summaries = aggregator.summarize(&options);
+        return Box::new(SummaryReport { options, summaries });
     }
 
-    print!("Term, ");
-    for i in 0..polls {
-        print!("{}, ", i)
+    let mut series = results
+        .map(|(replicate, PolledSeries { data, stop })| (replicate.id, stop, data))
+        .collect::<Vec<_>>();
+    series.sort_by_key(|(id, _, _)| *id);
+    Box::new(SeriesReport { polls, series })
+}
+
+/// Write the abundances recorded by `tracker` for `replicate`, if there is a `recorder`.
+///
+/// SAFETY: `panic!`s when the abundances cannot be written.
+fn write_abundances(
+    recorder: Option<&AbundanceRecorder>,
+    tracker: Option<AbundanceTracker>,
+    replicate: &Replicate,
+    reactor: &config::Reactor,
+) {
+    if let (Some(recorder), Some(tracker)) = (recorder, tracker) {
+        recorder
+            .write(replicate.id, tracker, reactor)
+            .unwrap_or_else(|e| {
+                panic!(
+                    "cannot write abundances of replicate {}: {}",
+                    replicate.id, e
+                )
+            });
     }
-    println!();
-    for (term, vec) in map.iter() {
-        print!("{}, ", term);
-        for c in vec {
-            print!("{}, ", c);
+}
+
+/// Poll `soup` with `poller`, see `Soup::simulate_and_poll`. If there is a `recorder`, also
+/// record the abundances of its species at each poll and write them for `replicate`.
+///
+/// SAFETY: `panic!`s when the abundances cannot be written.
+fn poll_soup<T>(
+    soup: &mut Soup,
+    cfg: &config::Ensemble,
+    replicate: &Replicate,
+    recorder: Option<&AbundanceRecorder>,
+    mut poller: impl FnMut(&Soup) -> T,
+) -> Vec<T> {
+    let mut tracker = recorder.map(|_| AbundanceTracker::new());
+    let polls = soup.simulate_and_poll(cfg.run_limit, cfg.polling_interval, false, |s| {
+        if let Some(tracker) = &mut tracker {
+            tracker.record_soup(s);
         }
-        println!();
-    }
-}
-
-pub async fn simulate_sample() {
-    let mut futures = FuturesUnordered::new();
-    let run_length = 10000000;
-    let polling_interval = 1000;
-    let polls = run_length / polling_interval;
-    let sample = read_inputs().collect::<Vec<Term>>();
-    for i in 0..1000 {
-        futures.push(spawn(simulate_soup_and_produce_entropies(
-            sample.clone().into_iter().cycle().take(10000),
-            i,
-            run_length,
-            polling_interval,
-        )));
-    }
-
-    print!("Soup, ");
-    for i in 0..polls {
-        print!("{}, ", i)
-    }
-    println!();
-    while let Some((id, data)) = futures.next().await {
-        print!("{}, ", id);
-        for i in data {
-            print!("{}, ", i)
+        poller(s)
+    });
+    write_abundances(recorder, tracker, replicate, &cfg.reactor_config);
+    polls
+}
+
+fn simulate_soup_and_poll(
+    sample: impl Iterator<Item = Term>,
+    cfg: &config::Ensemble,
+    replicate: Replicate,
+    observable: Observable,
+    probes: &config::Probes,
+    recorder: Option<&AbundanceRecorder>,
+) -> PolledSeries {
+    let mut soup = seed_soup(cfg, &replicate, sample);
+    let mut tracker = recorder.map(|_| AbundanceTracker::new());
+    let (data, stop) = soup.simulate_and_poll_until(
+        cfg.run_limit,
+        cfg.polling_interval,
+        false,
+        &cfg.stopping,
+        |s: &Soup| {
+            if let Some(tracker) = &mut tracker {
+                tracker.record_soup(s);
+            }
+            observable.measure(s, probes)
+        },
+    );
+    write_abundances(recorder, tracker, &replicate, &cfg.reactor_config);
+    PolledSeries { data, stop }
+}
+
+/// Look for Church addition in soups seeded from `stdin`
+pub struct AdditionSearch;
+
+/// When each soup of an addition search first contained addition
+#[derive(Debug, Serialize)]
+pub struct AdditionSearchReport {
+    /// Replicate, number of collisions when addition was first seen (if ever), and number of
+    /// polls before the soup stopped
+    pub soups: Vec<(usize, Option<usize>, usize)>,
+}
+
+impl Report for AdditionSearchReport {
+    fn write_csv(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "soup,found_at,polls")?;
+        for (id, found_at, polls) in &self.soups {
+            let found_at = found_at.map(|c| c.to_string()).unwrap_or_default();
+            writeln!(out, "{},{},{}", id, found_at, polls)?;
         }
-        println!();
+        Ok(())
+    }
+
+    fn write_json(&self, out: &mut dyn Write) -> io::Result<()> {
+        write_json(self, out)
+    }
+}
+
+fn simulate_additive_murder(
+    sample: impl Iterator<Item = Term>,
+    cfg: &config::Ensemble,
+    replicate: Replicate,
+) -> Vec<(usize, bool)> {
+    let mut soup = seed_soup(cfg, &replicate, sample);
+    let add = parse(r"\m.\n. m ((\m.\n. m (\n.\x.\y. x (n x y)) n) n) (\x.\y.y)", Classic).unwrap();
+    soup.simulate_and_poll_with_killer(cfg.run_limit, cfg.polling_interval, false, |s| {
+        let found = s.expressions().any(|e| e.is_isomorphic_to(&add));
+        ((s.collisions(), found), found)
+    })
+}
+
+impl Experiment for AdditionSearch {
+    fn name(&self) -> &'static str {
+        "addition-search"
+    }
+
+    fn description(&self) -> &'static str {
+        "Look for Church addition in soups seeded from stdin"
+    }
+
+    fn section<'a>(&self, cfg: &'a config::Experiments) -> &'a config::Ensemble {
+        &cfg.addition_search
+    }
+
+    fn section_mut<'a>(&self, cfg: &'a mut config::Experiments) -> &'a mut config::Ensemble {
+        &mut cfg.addition_search
+    }
+
+    fn reads_stdin(&self) -> bool {
+        true
+    }
+
+    fn run(&self, ctx: &Context) -> io::Result<Box<dyn Report>> {
+        let cfg = ctx.config.experiments.addition_search.clone();
+        let sample = Arc::new(ctx.inputs.to_vec());
+        let results = ctx.ensemble.run(cfg.replicates, move |replicate| {
+            simulate_additive_murder(
+                sample.iter().cloned().cycle().take(cfg.sample_size),
+                &cfg,
+                replicate,
+            )
+        })?;
+
+        let mut soups = results
+            .map(|(replicate, series)| {
+                let found_at = series.last().filter(|(_, found)| *found).map(|(c, _)| *c);
+                (replicate.id, found_at, series.len())
+            })
+            .collect::<Vec<_>>();
+        soups.sort();
+        Ok(Box::new(AdditionSearchReport { soups }))
+    }
+}
+
+/// Species counts over time of a single soup seeded from `stdin`
+pub struct SampleScan;
+
+/// Abundances recorded by a sample scan, and the file the species dictionary goes to, if any.
+/// The dictionary is written along with the report, so that a failure to write it is reported
+/// like any other output error.
+#[derive(Debug, Serialize)]
+pub struct ScanReport {
+    #[serde(flatten)]
+    pub abundances: Abundances,
+
+    #[serde(skip)]
+    pub species_file: Option<String>,
+}
+
+impl ScanReport {
+    fn write_species_file(&self) -> io::Result<()> {
+        let Some(path) = &self.species_file else {
+            return Ok(());
+        };
+        self.abundances.write_species_file(path).map_err(|e| {
+            io::Error::new(e.kind(), format!("cannot write species to {}: {}", path, e))
+        })
+    }
+}
+
+impl Report for ScanReport {
+    fn write_csv(&self, out: &mut dyn Write) -> io::Result<()> {
+        self.write_species_file()?;
+        self.abundances.write_records(out)
+    }
+
+    fn write_json(&self, out: &mut dyn Write) -> io::Result<()> {
+        self.write_species_file()?;
+        write_json(self, out)
+    }
+}
+
+impl Experiment for SampleScan {
+    fn name(&self) -> &'static str {
+        "sample-scan"
+    }
+
+    fn description(&self) -> &'static str {
+        "Record the number of copies of each species at each poll of a single soup seeded from \
+         stdin, as sparse poll,species,count records"
+    }
+
+    fn section<'a>(&self, cfg: &'a config::Experiments) -> &'a config::Ensemble {
+        &cfg.sample_scan.ensemble
+    }
+
+    fn section_mut<'a>(&self, cfg: &'a mut config::Experiments) -> &'a mut config::Ensemble {
+        &mut cfg.sample_scan.ensemble
+    }
+
+    fn reads_stdin(&self) -> bool {
+        true
+    }
+
+    fn parameters(&self, cfg: &config::Config) -> Vec<Parameter> {
+        let cfg = &cfg.experiments.sample_scan;
+        let mut parameters = ensemble_parameters(&cfg.ensemble);
+        parameters.extend([
+            Parameter {
+                name: "min_peak",
+                description: "Smallest peak number of copies of a recorded species",
+                value: cfg.abundances.min_peak.into(),
+            },
+            Parameter {
+                name: "top_k",
+                description: "Number of species with the highest peaks to record",
+                value: serde_json::to_value(cfg.abundances.top_k).unwrap(),
+            },
+            Parameter {
+                name: "species_file",
+                description: "File the species dictionary is written to",
+                value: serde_json::to_value(&cfg.species_file).unwrap(),
+            },
+        ]);
+        parameters
+    }
+
+    fn run(&self, ctx: &Context) -> io::Result<Box<dyn Report>> {
+        let scan = &ctx.config.experiments.sample_scan;
+        let cfg = &scan.ensemble;
+        let sample = ctx.inputs.to_vec();
+
+        let mut soup = seed_soup(
+            cfg,
+            &ctx.ensemble.replicate(0),
+            sample.into_iter().cycle().take(cfg.sample_size),
+        );
+        let mut tracker = AbundanceTracker::new();
+        soup.simulate_and_poll(cfg.run_limit, cfg.polling_interval, false, |s| {
+            tracker.record_soup(s)
+        });
+
+        let reactor = &cfg.reactor_config;
+        let classifier = Classifier::new(reactor.reduction_cutoff, reactor.size_cutoff);
+        Ok(Box::new(ScanReport {
+            abundances: tracker.finish(&scan.abundances, &classifier),
+            species_file: scan.species_file.clone(),
+        }))
+    }
+}
+
+/// Polled series of soups seeded from `stdin`
+pub struct SampleSimulate;
+
+impl Experiment for SampleSimulate {
+    fn name(&self) -> &'static str {
+        "sample-simulate"
+    }
+
+    fn description(&self) -> &'static str {
+        "Poll the observable in an ensemble of soups seeded from stdin"
+    }
+
+    fn section<'a>(&self, cfg: &'a config::Experiments) -> &'a config::Ensemble {
+        &cfg.sample_simulate
+    }
+
+    fn section_mut<'a>(&self, cfg: &'a mut config::Experiments) -> &'a mut config::Ensemble {
+        &mut cfg.sample_simulate
+    }
+
+    fn reads_stdin(&self) -> bool {
+        true
+    }
+
+    fn records_abundances(&self) -> bool {
+        true
+    }
+
+    fn parameters(&self, cfg: &config::Config) -> Vec<Parameter> {
+        stopping_parameters(&cfg.experiments.sample_simulate)
+    }
+
+    fn run(&self, ctx: &Context) -> io::Result<Box<dyn Report>> {
+        let cfg = ctx.config.experiments.sample_simulate.clone();
+        let polls = cfg.run_limit / cfg.polling_interval;
+        let observable = ctx.observable;
+        let probes = ctx.config.probe_config.clone();
+        let recorder = ctx.abundances.cloned();
+        let sample = Arc::new(ctx.inputs.to_vec());
+        let results = ctx.ensemble.run(cfg.replicates, move |replicate| {
+            simulate_soup_and_poll(
+                sample.iter().cloned().cycle().take(cfg.sample_size),
+                &cfg,
+                replicate,
+                observable,
+                &probes,
+                recorder.as_ref(),
+            )
+        })?;
+        Ok(series_report(results, polls, ctx.summary))
     }
 }
 
@@ -203,204 +679,1909 @@ where
     None
 }
 
-async fn simulate_soup_murder(
+fn simulate_soup_murder(
     sample: impl Iterator<Item = Term>,
-    id: usize,
-    run_length: usize,
-    polling_interval: usize,
-) -> (usize, Vec<Option<(Term, Term)>>) {
-    let mut soup = Soup::from_config(&config::Reactor {
-        rules: vec![String::from("\\x.\\y.x y")],
-        discard_copy_actions: false,
-        discard_identity: false,
-        discard_free_variable_expressions: true,
-        maintain_constant_population_size: true,
-        discard_parents: false,
-        reduction_cutoff: 512,
-        size_cutoff: 1024,
-        seed: config::ConfigSeed::new([0; 32]),
-    });
-    soup.perturb(sample);
-    let check_series =
-        soup.simulate_and_poll_with_killer(run_length, polling_interval, false, |s| {
-            let bests = s.k_most_frequent_exprs(10);
-            let pairs = pairwise_compare(&bests, not_xorset_test, false);
-            (pairs.clone(), pairs.is_some())
-        });
-    (id, check_series)
+    cfg: &config::Ensemble,
+    replicate: Replicate,
+) -> Vec<Option<Xorset>> {
+    let mut soup = seed_soup(cfg, &replicate, sample);
+    soup.simulate_and_poll_with_killer(cfg.run_limit, cfg.polling_interval, false, |s| {
+        let bests = s.k_most_frequent_exprs(10);
+        let pairs = pairwise_compare(&bests, not_xorset_test, false);
+        let found = pairs.is_some();
+        (pairs.map(|(a, b)| (a.to_string(), b.to_string())), found)
+    })
 }
 
-pub async fn look_for_xorset() {
-    let mut gen = BTreeGen::from_config(&config::BTreeGen {
-        size: 20,
-        freevar_generation_probability: 0.2,
-        standardization: crate::generators::Standardization::Prefix,
-        n_max_free_vars: 6,
-        seed: config::ConfigSeed::new([0; 32]),
-    });
-    let mut futures = FuturesUnordered::new();
-    let run_length = 10000000;
-    let polling_interval = 1000;
-    for i in 0..1000 {
-        let sample = gen.generate_n(10000);
-        futures.push(spawn(simulate_soup_murder(
-            sample.into_iter(),
-            i,
-            run_length,
-            polling_interval,
-        )));
-    }
-
-    print!("Soup, ");
-    println!();
-    while let Some((id, series)) = futures.next().await {
-        print!("{}, ", id);
-        for i in series {
-            if i.is_some() {
-                print!("{:?}, ", i)
-            }
+/// Look for xorsets in soups seeded from the generator
+pub struct XorsetSearch;
+
+/// A pair of expressions forming a xorset
+pub type Xorset = (String, String);
+
+/// The first xorset found in each soup
+#[derive(Debug, Serialize)]
+pub struct XorsetSearchReport {
+    /// Replicate, the xorset found (if any), and the number of polls before the soup stopped
+    pub soups: Vec<(usize, Option<Xorset>, usize)>,
+}
+
+impl Report for XorsetSearchReport {
+    fn write_csv(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "soup,a,b,polls")?;
+        for (id, pair, polls) in &self.soups {
+            let (a, b) = pair.clone().unwrap_or_default();
+            writeln!(out, "{},{},{},{}", id, a, b, polls)?;
         }
-        println!();
+        Ok(())
+    }
+
+    fn write_json(&self, out: &mut dyn Write) -> io::Result<()> {
+        write_json(self, out)
     }
 }
 
-async fn simulate_soup(
-    sample: impl Iterator<Item = Term>,
-    id: usize,
-    run_length: usize,
-) -> (Soup, usize, f32) {
-    let mut soup = Soup::from_config(&config::Reactor {
-        rules: vec![String::from("\\x.\\y.x y")],
-        discard_copy_actions: false,
-        discard_identity: false,
-        discard_free_variable_expressions: true,
-        maintain_constant_population_size: true,
-        discard_parents: false,
-        reduction_cutoff: 512,
-        size_cutoff: 1024,
-        seed: config::ConfigSeed::new([0; 32]),
-    });
-    soup.perturb(sample);
-    let n_successes = soup.simulate_for(run_length, false);
-    let failure_rate = 1f32 - n_successes as f32 / run_length as f32;
-    (soup, id, failure_rate)
+impl Experiment for XorsetSearch {
+    fn name(&self) -> &'static str {
+        "xorset-search"
+    }
+
+    fn description(&self) -> &'static str {
+        "Look for xorsets among the most frequent species of soups seeded from the generator"
+    }
+
+    fn section<'a>(&self, cfg: &'a config::Experiments) -> &'a config::Ensemble {
+        &cfg.xorset_search
+    }
+
+    fn section_mut<'a>(&self, cfg: &'a mut config::Experiments) -> &'a mut config::Ensemble {
+        &mut cfg.xorset_search
+    }
+
+    fn run(&self, ctx: &Context) -> io::Result<Box<dyn Report>> {
+        let generator = ctx.config.generator_config.clone();
+        let cfg = ctx.config.experiments.xorset_search.clone();
+        let results = ctx.ensemble.run(cfg.replicates, move |replicate| {
+            let sample = generate_sample(&generator, &replicate, cfg.sample_size);
+            simulate_soup_murder(sample.into_iter(), &cfg, replicate)
+        })?;
+
+        let mut soups = results
+            .map(|(replicate, series)| {
+                let polls = series.len();
+                (replicate.id, series.into_iter().flatten().next(), polls)
+            })
+            .collect::<Vec<_>>();
+        soups.sort();
+        Ok(Box::new(XorsetSearchReport { soups }))
+    }
 }
 
-async fn simulate_soup_and_produce_entropies(
-    sample: impl Iterator<Item = Term>,
-    id: usize,
-    run_length: usize,
-    polling_interval: usize,
-) -> (usize, Vec<f32>) {
-    let mut seed: [u8; 32] = [0; 32];
-    let bytes = id.to_le_bytes();
-    seed[..bytes.len()].copy_from_slice(&bytes);
-
-    let mut soup = Soup::from_config(&config::Reactor {
-        rules: vec![String::from("\\x.\\y.x y")],
-        discard_copy_actions: false,
-        discard_identity: false,
-        discard_free_variable_expressions: true,
-        maintain_constant_population_size: true,
-        discard_parents: false,
-        reduction_cutoff: 512,
-        size_cutoff: 1024,
-        seed: config::ConfigSeed::new(seed),
-    });
-    soup.perturb(sample);
-    let data = soup.simulate_and_poll(run_length, polling_interval, false, |s: &Soup| {
-        s.population_entropy()
-    });
-    (id, data)
+/// Persistence of a candidate xorset, alone and mixed with generated expressions
+pub struct XorsetStability;
+
+/// Number of copies of each member of the xorset at a single poll
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct XorsetCensus {
+    pub a: usize,
+    pub b: usize,
+
+    /// Number of expressions in the soup
+    pub total: usize,
 }
 
-pub async fn entropy_series() {
-    let mut gen = BTreeGen::from_config(&config::BTreeGen {
-        size: 20,
-        freevar_generation_probability: 0.2,
-        standardization: crate::generators::Standardization::Prefix,
-        n_max_free_vars: 6,
-        seed: config::ConfigSeed::new([0; 32]),
-    });
-    let mut futures = FuturesUnordered::new();
-    let run_length = 10000000;
-    let polling_interval = 1000;
-    let polls = run_length / polling_interval;
-    for i in 0..1000 {
-        let sample = gen.generate_n(10000);
-        futures.push(spawn(simulate_soup_and_produce_entropies(
-            sample.into_iter(),
-            i,
-            run_length,
-            polling_interval,
-        )));
-    }
-
-    print!("Soup, ");
-    for i in 0..polls {
-        print!("{}, ", i)
-    }
-    println!();
-    while let Some((id, data)) = futures.next().await {
-        print!("{}, ", id);
-        for i in data {
-            print!("{}, ", i)
+/// Outcome of a single soup of the xorset stability experiment
+#[derive(Debug, Serialize)]
+pub struct StabilityRun {
+    /// Fraction of the xorset expressions that started as copies of `a`
+    pub proportion: f64,
+
+    /// Fraction of the soup that started as generated expressions
+    pub background: f64,
+
+    /// Index of the soup among the soups with the same initial composition
+    pub replicate: usize,
+
+    /// Whether both `a` and `b` are still present at the end of the run
+    pub persisted: bool,
+
+    /// Reactions until `a` or `b` first disappeared from the soup
+    pub lost_at: Option<usize>,
+
+    /// Reactions until `a` and `b` first made up the dominant fraction of the soup
+    pub dominant_at: Option<usize>,
+
+    /// Census of the xorset at each poll
+    pub census: Vec<XorsetCensus>,
+}
+
+/// Outcome of every soup of the xorset stability experiment
+#[derive(Debug, Serialize)]
+pub struct StabilityReport {
+    pub runs: Vec<StabilityRun>,
+}
+
+/// Persistence of the xorset for each initial composition, across replicates
+#[derive(Debug, Serialize)]
+pub struct StabilitySummary {
+    /// Proportion, background fraction, number of soups, fraction of soups in which the xorset
+    /// persisted, and median number of reactions until it was lost in the soups that lost it
+    pub conditions: Vec<(f64, f64, usize, f64, Option<f64>)>,
+}
+
+impl XorsetCensus {
+    fn take(soup: &Soup, a: &Term, b: &Term) -> Self {
+        let count = |t: &Term| soup.expressions().filter(|e| e.is_isomorphic_to(t)).count();
+        XorsetCensus {
+            a: count(a),
+            b: count(b),
+            total: soup.len(),
         }
-        println!();
+    }
+
+    fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        (self.a + self.b) as f64 / self.total as f64
     }
 }
 
-pub async fn entropy_test() {
-    let mut gen = BTreeGen::from_config(&config::BTreeGen {
-        size: 20,
-        freevar_generation_probability: 0.2,
-        standardization: crate::generators::Standardization::Prefix,
-        n_max_free_vars: 6,
-        seed: config::ConfigSeed::new([0; 32]),
-    });
-    let mut futures = FuturesUnordered::new();
-    for i in 0..1000 {
-        let sample = gen.generate_n(10000);
-        futures.push(spawn(simulate_soup(sample.into_iter(), i, 10000000)));
+impl StabilityRun {
+    fn from_census(
+        cfg: &config::XorsetStability,
+        condition: (f64, f64),
+        replicate: usize,
+        census: Vec<XorsetCensus>,
+    ) -> Self {
+        let interval = cfg.ensemble.polling_interval;
+        let lost_at = census
+            .iter()
+            .position(|c| c.a == 0 || c.b == 0)
+            .map(|i| i * interval);
+        let dominant_at = census
+            .iter()
+            .position(|c| c.fraction() >= cfg.dominance)
+            .map(|i| i * interval);
+        StabilityRun {
+            proportion: condition.0,
+            background: condition.1,
+            replicate,
+            persisted: census.last().is_some_and(|c| c.a > 0 && c.b > 0),
+            lost_at,
+            dominant_at,
+            census,
+        }
     }
+}
 
-    let mut data = Vec::new();
-    println!("Soup, Entropy, Failure rate");
-    while let Some((soup, id, failure_rate)) = futures.next().await {
-        let entropy = soup.population_entropy();
-        println!("{}, {}, {}", id, entropy, failure_rate);
-        data.push(entropy);
+impl Report for StabilityReport {
+    fn write_csv(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "proportion,background,replicate,persisted,lost_at,dominant_at,final_fraction"
+        )?;
+        for run in &self.runs {
+            let lost_at = run.lost_at.map(|n| n.to_string()).unwrap_or_default();
+            let dominant_at = run.dominant_at.map(|n| n.to_string()).unwrap_or_default();
+            let fraction = run.census.last().map(|c| c.fraction()).unwrap_or(0.0);
+            writeln!(
+                out,
+                "{},{},{},{},{},{},{}",
+                run.proportion,
+                run.background,
+                run.replicate,
+                run.persisted,
+                lost_at,
+                dominant_at,
+                fraction
+            )?;
+        }
+        Ok(())
     }
 
-    plot_histogram(&data).unwrap();
+    fn write_json(&self, out: &mut dyn Write) -> io::Result<()> {
+        write_json(self, out)
+    }
 }
 
-pub fn sync_entropy_test() {
-    let mut gen = BTreeGen::from_config(&config::BTreeGen {
-        size: 20,
-        freevar_generation_probability: 0.2,
-        standardization: crate::generators::Standardization::Prefix,
-        n_max_free_vars: 6,
-        seed: config::ConfigSeed::new([0; 32]),
-    });
+impl Report for StabilitySummary {
+    fn write_csv(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "proportion,background,n,persistence,median_lost_at")?;
+        for (proportion, background, n, persistence, lost_at) in &self.conditions {
+            let lost_at = lost_at.map(|x| x.to_string()).unwrap_or_default();
+            writeln!(
+                out,
+                "{},{},{},{},{}",
+                proportion, background, n, persistence, lost_at
+            )?;
+        }
+        Ok(())
+    }
 
-    for i in 0..100 {
-        let sample = gen.generate_n(1000);
-        let mut soup = Soup::from_config(&config::Reactor {
-            rules: vec![String::from("\\x.\\y.x y")],
-            discard_copy_actions: false,
-            discard_identity: false,
-            discard_free_variable_expressions: true,
-            maintain_constant_population_size: true,
-            discard_parents: false,
-            reduction_cutoff: 512,
-            size_cutoff: 1024,
-            seed: config::ConfigSeed::new([0; 32]),
-        });
-        soup.perturb(sample);
-        soup.simulate_for(100000, false);
-        let entropy = soup.population_entropy();
-        println!("{}: {}", i, entropy);
+    fn write_json(&self, out: &mut dyn Write) -> io::Result<()> {
+        write_json(self, out)
+    }
+}
+
+/// Summarize stability runs for each initial composition. `runs` must be sorted by condition.
+fn summarize_stability(runs: &[StabilityRun]) -> StabilitySummary {
+    let conditions = runs
+        .chunk_by(|x, y| x.proportion == y.proportion && x.background == y.background)
+        .map(|group| {
+            let n = group.len();
+            let persisted = group.iter().filter(|r| r.persisted).count();
+            let mut lost = group
+                .iter()
+                .filter_map(|r| r.lost_at.map(|x| x as f64))
+                .collect::<Vec<_>>();
+            lost.sort_by(f64::total_cmp);
+            let median = (!lost.is_empty()).then(|| statistics::quantile(&lost, 0.5));
+            let first = &group[0];
+            (
+                first.proportion,
+                first.background,
+                n,
+                persisted as f64 / n as f64,
+                median,
+            )
+        })
+        .collect();
+    StabilitySummary { conditions }
+}
+
+impl Experiment for XorsetStability {
+    fn name(&self) -> &'static str {
+        "xorset-stability"
+    }
+
+    fn description(&self) -> &'static str {
+        "Seed soups with a candidate xorset in varying proportions, alone and mixed with \
+         generated expressions, and measure whether and how long it persists"
+    }
+
+    fn section<'a>(&self, cfg: &'a config::Experiments) -> &'a config::Ensemble {
+        &cfg.xorset_stability.ensemble
+    }
+
+    fn section_mut<'a>(&self, cfg: &'a mut config::Experiments) -> &'a mut config::Ensemble {
+        &mut cfg.xorset_stability.ensemble
+    }
+
+    fn parameters(&self, cfg: &config::Config) -> Vec<Parameter> {
+        let cfg = &cfg.experiments.xorset_stability;
+        let mut parameters = ensemble_parameters(&cfg.ensemble);
+        parameters.extend([
+            Parameter {
+                name: "a",
+                description: "First expression of the candidate xorset",
+                value: cfg.a.clone().into(),
+            },
+            Parameter {
+                name: "b",
+                description: "Second expression of the candidate xorset",
+                value: cfg.b.clone().into(),
+            },
+            Parameter {
+                name: "proportions",
+                description: "Fractions of the xorset expressions that are copies of `a`",
+                value: cfg.proportions.clone().into(),
+            },
+            Parameter {
+                name: "background_fractions",
+                description: "Fractions of the soup drawn from the generator",
+                value: cfg.background_fractions.clone().into(),
+            },
+            Parameter {
+                name: "dominance",
+                description: "Fraction of the soup above which the xorset dominates it",
+                value: cfg.dominance.into(),
+            },
+        ]);
+        parameters
+    }
+
+    /// SAFETY: `panic!`s when `a` or `b` cannot be parsed.
+    fn run(&self, ctx: &Context) -> io::Result<Box<dyn Report>> {
+        let generator = ctx.config.generator_config.clone();
+        let cfg = ctx.config.experiments.xorset_stability.clone();
+        let a = parse(&cfg.a, Classic).unwrap();
+        let b = parse(&cfg.b, Classic).unwrap();
+
+        let conditions = cfg
+            .proportions
+            .iter()
+            .flat_map(|p| cfg.background_fractions.iter().map(move |bg| (*p, *bg)))
+            .collect::<Vec<_>>();
+        let replicates = cfg.ensemble.replicates;
+        let n = conditions.len() * replicates;
+
+        let job_cfg = cfg.clone();
+        let job_conditions = conditions.clone();
+        let results = ctx.ensemble.run(n, move |replicate| {
+            let cfg = &job_cfg.ensemble;
+            let (proportion, background) = job_conditions[replicate.id / replicates];
+            let n_xorset = (cfg.sample_size as f64 * (1.0 - background)).round() as usize;
+            let n_a = (n_xorset as f64 * proportion).round() as usize;
+
+            let mut sample = generate_sample(&generator, &replicate, cfg.sample_size - n_xorset);
+            sample.extend(std::iter::repeat(a.clone()).take(n_a));
+            sample.extend(std::iter::repeat(b.clone()).take(n_xorset - n_a));
+
+            let mut soup = seed_soup(cfg, &replicate, sample);
+            soup.simulate_and_poll(cfg.run_limit, cfg.polling_interval, false, |s| {
+                XorsetCensus::take(s, &a, &b)
+            })
+        })?;
+
+        let mut runs = results
+            .map(|(replicate, census)| {
+                let condition = conditions[replicate.id / replicates];
+                (replicate.id, condition, census)
+            })
+            .collect::<Vec<_>>();
+        runs.sort_by_key(|(id, _, _)| *id);
+        let runs = runs
+            .into_iter()
+            .map(|(id, condition, census)| {
+                StabilityRun::from_census(&cfg, condition, id % replicates, census)
+            })
+            .collect::<Vec<_>>();
+
+        if ctx.summary {
+            return Ok(Box::new(summarize_stability(&runs)));
+        }
+        Ok(Box::new(StabilityReport { runs }))
+    }
+}
+
+/// Look for a set of target expressions in soups seeded from the generator
+pub struct TargetSearch;
+
+/// Outcome of the search for one target in one soup
+#[derive(Debug, Serialize)]
+pub struct TargetRun {
+    pub replicate: usize,
+    pub target: String,
+
+    /// Number of collisions at the first poll at which the target was present
+    pub first_seen: Option<usize>,
+
+    /// Largest number of copies of the target at any poll
+    pub peak: usize,
+
+    /// Number of copies of the target at the last poll
+    pub last: usize,
+
+    /// Mean number of copies of the target over the polls from its first appearance onwards
+    pub mean_after: Option<f64>,
+}
+
+/// Outcome of the search for each target in each soup
+#[derive(Debug, Serialize)]
+pub struct TargetSearchReport {
+    pub runs: Vec<TargetRun>,
+}
+
+/// Outcome of the search for one target, across the ensemble
+#[derive(Debug, Serialize)]
+pub struct TargetStats {
+    pub target: String,
+
+    /// Number of soups
+    pub n: usize,
+
+    /// Fraction of soups in which the target appeared, `None` if there are no soups
+    pub found: Option<f64>,
+
+    /// Median number of collisions until the target first appeared, in the soups where it did
+    pub median_first_seen: Option<f64>,
+
+    /// Mean of `TargetRun::mean_after`, in the soups where the target appeared
+    pub mean_after: Option<f64>,
+}
+
+/// Outcome of the search for each target, across the ensemble
+#[derive(Debug, Serialize)]
+pub struct TargetSummary {
+    pub targets: Vec<TargetStats>,
+}
+
+impl Report for TargetSearchReport {
+    fn write_csv(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "soup,target,first_seen,peak,last,mean_after")?;
+        for run in &self.runs {
+            let first_seen = run.first_seen.map(|c| c.to_string()).unwrap_or_default();
+            let mean_after = run.mean_after.map(|m| m.to_string()).unwrap_or_default();
+            writeln!(
+                out,
+                "{},{},{},{},{},{}",
+                run.replicate, run.target, first_seen, run.peak, run.last, mean_after
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_json(&self, out: &mut dyn Write) -> io::Result<()> {
+        write_json(self, out)
+    }
+}
+
+impl Report for TargetSummary {
+    fn write_csv(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "target,n,found,median_first_seen,mean_after")?;
+        for t in &self.targets {
+            let found = t.found.map(|f| f.to_string());
+            let first_seen = t.median_first_seen.map(|c| c.to_string());
+            let mean_after = t.mean_after.map(|m| m.to_string());
+            writeln!(
+                out,
+                "{},{},{},{},{}",
+                t.target,
+                t.n,
+                found.unwrap_or_default(),
+                first_seen.unwrap_or_default(),
+                mean_after.unwrap_or_default()
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_json(&self, out: &mut dyn Write) -> io::Result<()> {
+        write_json(self, out)
+    }
+}
+
+/// Read targets from `path`, one per line as a name followed by an expression.
+fn read_targets(path: &str) -> io::Result<Vec<(String, String)>> {
+    let contents = read_to_string(path)?;
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (name, expr) = line.split_once(char::is_whitespace).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("target {} has no expression", line),
+                )
+            })?;
+            Ok((name.to_string(), expr.trim().to_string()))
+        })
+        .collect()
+}
+
+/// All targets of `cfg`, parsed and reduced to normal form with the limits of the reactor.
+///
+/// SAFETY: `panic!`s when the targets file cannot be read, a target cannot be parsed, or two
+/// targets have the same name.
+fn load_targets(cfg: &config::TargetSearch) -> Vec<(String, Term)> {
+    let mut targets = cfg
+        .targets
+        .iter()
+        .map(|(name, expr)| (name.clone(), expr.clone()))
+        .collect::<Vec<_>>();
+    if let Some(path) = &cfg.targets_file {
+        let more = read_targets(path)
+            .unwrap_or_else(|e| panic!("cannot read targets from {}: {}", path, e));
+        targets.extend(more);
+    }
+    let mut names = HashSet::new();
+    for (name, _) in &targets {
+        if !names.insert(name) {
+            panic!("target {} is defined more than once", name);
+        }
+    }
+
+    let reactor = &cfg.ensemble.reactor_config;
+    targets
+        .into_iter()
+        .map(|(name, expr)| {
+            let mut term = parse(&expr, Classic)
+                .unwrap_or_else(|e| panic!("cannot parse target {}: {:?}", name, e));
+            let _ = reduce_with_limit(&mut term, reactor.reduction_cutoff, reactor.size_cutoff);
+            (name, term)
+        })
+        .collect()
+}
+
+impl TargetRun {
+    fn from_polls(replicate: usize, target: String, polls: &[(usize, usize)]) -> Self {
+        let first = polls.iter().position(|(_, count)| *count > 0);
+        let after = first.map(|i| &polls[i..]).unwrap_or_default();
+        TargetRun {
+            replicate,
+            target,
+            first_seen: first.map(|i| polls[i].0),
+            peak: polls.iter().map(|(_, count)| *count).max().unwrap_or(0),
+            last: polls.last().map(|(_, count)| *count).unwrap_or(0),
+            mean_after: first.map(|_| {
+                after.iter().map(|(_, count)| *count as f64).sum::<f64>() / after.len() as f64
+            }),
+        }
+    }
+}
+
+/// Summarize target runs across the ensemble, in the order in which targets are listed.
+fn summarize_targets(targets: &[(String, Term)], runs: &[TargetRun]) -> TargetSummary {
+    let targets = targets
+        .iter()
+        .map(|(name, _)| {
+            let runs = runs
+                .iter()
+                .filter(|r| r.target == *name)
+                .collect::<Vec<_>>();
+            let n = runs.len();
+            let mut first_seen = runs
+                .iter()
+                .filter_map(|r| r.first_seen.map(|c| c as f64))
+                .collect::<Vec<_>>();
+            first_seen.sort_by(f64::total_cmp);
+            let found = first_seen.len();
+            TargetStats {
+                target: name.clone(),
+                n,
+                found: (n > 0).then(|| found as f64 / n as f64),
+                median_first_seen: (found > 0).then(|| statistics::quantile(&first_seen, 0.5)),
+                mean_after: (found > 0)
+                    .then(|| runs.iter().filter_map(|r| r.mean_after).sum::<f64>() / found as f64),
+            }
+        })
+        .collect();
+    TargetSummary { targets }
+}
+
+impl Experiment for TargetSearch {
+    fn name(&self) -> &'static str {
+        "target-search"
+    }
+
+    fn description(&self) -> &'static str {
+        "Look for a set of target expressions in soups seeded from the generator, and track \
+         their abundance once they appear"
+    }
+
+    fn section<'a>(&self, cfg: &'a config::Experiments) -> &'a config::Ensemble {
+        &cfg.target_search.ensemble
+    }
+
+    fn section_mut<'a>(&self, cfg: &'a mut config::Experiments) -> &'a mut config::Ensemble {
+        &mut cfg.target_search.ensemble
+    }
+
+    fn parameters(&self, cfg: &config::Config) -> Vec<Parameter> {
+        let cfg = &cfg.experiments.target_search;
+        let mut parameters = ensemble_parameters(&cfg.ensemble);
+        parameters.extend([
+            Parameter {
+                name: "targets",
+                description: "Target expressions, keyed by name",
+                value: serde_json::to_value(&cfg.targets).unwrap(),
+            },
+            Parameter {
+                name: "targets_file",
+                description: "File with more targets, one name and expression per line",
+                value: serde_json::to_value(&cfg.targets_file).unwrap(),
+            },
+            Parameter {
+                name: "match_behavior",
+                description: "Match targets by behavior on the probes in `probe_config`",
+                value: cfg.match_behavior.into(),
+            },
+        ]);
+        parameters
+    }
+
+    fn run(&self, ctx: &Context) -> io::Result<Box<dyn Report>> {
+        let generator = ctx.config.generator_config.clone();
+        let cfg = ctx.config.experiments.target_search.ensemble.clone();
+        let targets = Arc::new(load_targets(&ctx.config.experiments.target_search));
+
+        let prober = ctx
+            .config
+            .experiments
+            .target_search
+            .match_behavior
+            .then(|| Prober::from_config(&ctx.config.probe_config));
+
+        let job_targets = Arc::clone(&targets);
+        let results = ctx.ensemble.run(cfg.replicates, move |replicate| {
+            let sample = generate_sample(&generator, &replicate, cfg.sample_size);
+            let mut soup = seed_soup(&cfg, &replicate, sample);
+            soup.simulate_and_poll(cfg.run_limit, cfg.polling_interval, false, |s| {
+                let counts = job_targets
+                    .iter()
+                    .map(|(_, t)| match &prober {
+                        Some(prober) => s.count_behaving_like(t, prober),
+                        None => s.expressions().filter(|e| e.is_isomorphic_to(t)).count(),
+                    })
+                    .collect::<Vec<_>>();
+                (s.collisions(), counts)
+            })
+        })?;
+
+        let mut soups = results.collect::<Vec<_>>();
+        soups.sort_by_key(|(replicate, _)| replicate.id);
+        let runs = soups
+            .iter()
+            .flat_map(|(replicate, polls)| {
+                targets.iter().enumerate().map(|(i, (name, _))| {
+                    let series = polls
+                        .iter()
+                        .map(|(collisions, counts)| (*collisions, counts[i]))
+                        .collect::<Vec<_>>();
+                    TargetRun::from_polls(replicate.id, name.clone(), &series)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if ctx.summary {
+            return Ok(Box::new(summarize_targets(&targets, &runs)));
+        }
+        Ok(Box::new(TargetSearchReport { runs }))
+    }
+}
+
+/// Rank the most frequent species of soups seeded from the generator by how they replicate
+pub struct ReplicatorSearch;
+
+/// A ranked species of one soup, see `replication::Replicator`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplicatorRow {
+    pub rank: usize,
+    pub expr: String,
+    pub name: Option<String>,
+    pub count: u32,
+    pub self_copy: bool,
+    pub left_copy_rate: f32,
+    pub right_copy_rate: f32,
+
+    /// Ranks of the species this one regenerates, and is regenerated by
+    pub partners: Vec<usize>,
+}
+
+/// The ranked species of each soup
+#[derive(Debug, Serialize)]
+pub struct ReplicatorReport {
+    pub soups: Vec<(usize, Vec<ReplicatorRow>)>,
+}
+
+impl Report for ReplicatorReport {
+    fn write_csv(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "soup,rank,expr,name,count,self_copy,left_copy_rate,right_copy_rate,partners"
+        )?;
+        for (id, rows) in &self.soups {
+            for row in rows {
+                let partners = row
+                    .partners
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect::<Vec<_>>();
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{},{},{},{}",
+                    id,
+                    row.rank,
+                    row.expr,
+                    row.name.as_deref().unwrap_or_default(),
+                    row.count,
+                    row.self_copy,
+                    row.left_copy_rate,
+                    row.right_copy_rate,
+                    partners.join(" ")
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_json(&self, out: &mut dyn Write) -> io::Result<()> {
+        write_json(self, out)
+    }
+}
+
+impl Experiment for ReplicatorSearch {
+    fn name(&self) -> &'static str {
+        "replicator-search"
+    }
+
+    fn description(&self) -> &'static str {
+        "Rank the most frequent species of soups seeded from the generator by how often they \
+         copy themselves, and find pairs that regenerate each other"
+    }
+
+    fn section<'a>(&self, cfg: &'a config::Experiments) -> &'a config::Ensemble {
+        &cfg.replicator_search.ensemble
+    }
+
+    fn section_mut<'a>(&self, cfg: &'a mut config::Experiments) -> &'a mut config::Ensemble {
+        &mut cfg.replicator_search.ensemble
+    }
+
+    fn parameters(&self, cfg: &config::Config) -> Vec<Parameter> {
+        let cfg = &cfg.experiments.replicator_search;
+        let mut parameters = ensemble_parameters(&cfg.ensemble);
+        parameters.push(Parameter {
+            name: "top_k",
+            description: "Number of most frequent species to rank in each soup",
+            value: cfg.top_k.into(),
+        });
+        parameters
+    }
+
+    fn run(&self, ctx: &Context) -> io::Result<Box<dyn Report>> {
+        let generator = ctx.config.generator_config.clone();
+        let cfg = ctx.config.experiments.replicator_search.ensemble.clone();
+        let top_k = ctx.config.experiments.replicator_search.top_k;
+        let results = ctx.ensemble.run(cfg.replicates, move |replicate| {
+            let sample = generate_sample(&generator, &replicate, cfg.sample_size);
+            let mut soup = seed_soup(&cfg, &replicate, sample);
+            soup.simulate_for(cfg.run_limit, false);
+
+            let reactor = &cfg.reactor_config;
+            let classifier = Classifier::new(reactor.reduction_cutoff, reactor.size_cutoff);
+            soup.replicators(top_k)
+                .into_iter()
+                .enumerate()
+                .map(|(rank, r)| ReplicatorRow {
+                    rank,
+                    expr: r.expr.to_string(),
+                    name: classifier.name(&r.expr),
+                    count: r.count,
+                    self_copy: r.self_copy,
+                    left_copy_rate: r.left_copy_rate,
+                    right_copy_rate: r.right_copy_rate,
+                    partners: r.partners,
+                })
+                .collect::<Vec<_>>()
+        })?;
+
+        let mut soups = results
+            .map(|(replicate, rows)| (replicate.id, rows))
+            .collect::<Vec<_>>();
+        soups.sort_by_key(|(id, _)| *id);
+        Ok(Box::new(ReplicatorReport { soups }))
+    }
+}
+
+/// Products of the most frequent species of a soup seeded from the generator
+pub struct Interactions;
+
+/// Interaction matrix of the most frequent species of a soup, see `InteractionMatrix`
+pub struct InteractionReport {
+    matrix: InteractionMatrix,
+    names: Vec<Option<String>>,
+}
+
+impl Report for InteractionReport {
+    fn write_csv(&self, out: &mut dyn Write) -> io::Result<()> {
+        self.matrix.write_csv(out)
+    }
+
+    fn write_json(&self, out: &mut dyn Write) -> io::Result<()> {
+        let m = &self.matrix;
+        let species = m
+            .species()
+            .iter()
+            .zip(&self.names)
+            .map(|((expr, count), name)| {
+                serde_json::json!({ "expr": expr.to_string(), "name": name, "count": count })
+            })
+            .collect::<Vec<_>>();
+        let products = (0..m.n_rules())
+            .map(|rule| {
+                (0..m.len())
+                    .map(|left| {
+                        (0..m.len())
+                            .map(|right| match m.get(left, right, rule) {
+                                Product::Species(i) => serde_json::json!(i),
+                                p => serde_json::json!(p.to_string()),
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        // Products are indices of species, or "outside" or "failed", by rule, left and right
+        let report = serde_json::json!({ "species": species, "products": products });
+        write_json(&report, out)
+    }
+}
+
+impl Experiment for Interactions {
+    fn name(&self) -> &'static str {
+        "interaction-matrix"
+    }
+
+    fn description(&self) -> &'static str {
+        "Tabulate the products of every pair of the most frequent species of a soup seeded from \
+         the generator, under every rule"
+    }
+
+    fn section<'a>(&self, cfg: &'a config::Experiments) -> &'a config::Ensemble {
+        &cfg.interactions.ensemble
+    }
+
+    fn section_mut<'a>(&self, cfg: &'a mut config::Experiments) -> &'a mut config::Ensemble {
+        &mut cfg.interactions.ensemble
+    }
+
+    fn parameters(&self, cfg: &config::Config) -> Vec<Parameter> {
+        let cfg = &cfg.experiments.interactions;
+        let mut parameters = ensemble_parameters(&cfg.ensemble);
+        parameters.extend([
+            Parameter {
+                name: "top_k",
+                description: "Number of most frequent species in the matrix",
+                value: cfg.top_k.into(),
+            },
+            Parameter {
+                name: "heatmap",
+                description: "Draw a heatmap of each rule at `<heatmap>-<rule>.png`",
+                value: serde_json::to_value(&cfg.heatmap).unwrap(),
+            },
+        ]);
+        parameters
+    }
+
+    fn run(&self, ctx: &Context) -> io::Result<Box<dyn Report>> {
+        let cfg = &ctx.config.experiments.interactions;
+        let ensemble = &cfg.ensemble;
+        let replicate = ctx.ensemble.replicate(0);
+        let sample = generate_sample(
+            &ctx.config.generator_config,
+            &replicate,
+            ensemble.sample_size,
+        );
+        let mut soup = seed_soup(ensemble, &replicate, sample);
+        soup.simulate_for(ensemble.run_limit, false);
+
+        let matrix = InteractionMatrix::from_soup(&soup, cfg.top_k);
+        if let Some(prefix) = &cfg.heatmap {
+            for rule in 0..matrix.n_rules() {
+                let path = format!("{}-{}.png", prefix, rule);
+                matrix.plot_heatmap(&path, rule).map_err(|e| {
+                    io::Error::other(format!("cannot draw heatmap {}: {}", path, e))
+                })?;
+            }
+        }
+
+        let reactor = &ensemble.reactor_config;
+        let classifier = Classifier::new(reactor.reduction_cutoff, reactor.size_cutoff);
+        let names = matrix
+            .species()
+            .iter()
+            .map(|(expr, _)| classifier.name(expr))
+            .collect();
+        Ok(Box::new(InteractionReport { matrix, names }))
+    }
+}
+
+/// Look for closed algebraic structures among the frequent species of soups seeded from the
+/// generator
+pub struct AlgebraSearch;
+
+/// A closed subset of species of one soup, see `algebra::Structure`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StructureRow {
+    pub rule: usize,
+    pub members: Vec<String>,
+
+    /// Operation table on indices of `members`
+    pub table: Vec<Vec<usize>>,
+    pub associative: bool,
+    pub commutative: bool,
+
+    /// Index of the identity element in `members`
+    pub identity: Option<usize>,
+    pub inverses: bool,
+    pub isomorphic_to: Vec<String>,
+}
+
+/// The closed subsets found in each soup
+#[derive(Debug, Serialize)]
+pub struct AlgebraReport {
+    pub soups: Vec<(usize, Vec<StructureRow>)>,
+}
+
+impl Report for AlgebraReport {
+    fn write_csv(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "soup,rule,size,members,table,associative,commutative,identity,inverses,isomorphic_to"
+        )?;
+        for (id, rows) in &self.soups {
+            for row in rows {
+                let identity = row.identity.map(|i| i.to_string()).unwrap_or_default();
+                let table = row
+                    .table
+                    .iter()
+                    .map(|r| {
+                        r.iter()
+                            .map(|p| p.to_string())
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    })
+                    .collect::<Vec<_>>();
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{},{},{},{},{}",
+                    id,
+                    row.rule,
+                    row.members.len(),
+                    row.members.join(";"),
+                    table.join(";"),
+                    row.associative,
+                    row.commutative,
+                    identity,
+                    row.inverses,
+                    row.isomorphic_to.join(";")
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_json(&self, out: &mut dyn Write) -> io::Result<()> {
+        write_json(self, out)
+    }
+}
+
+impl Experiment for AlgebraSearch {
+    fn name(&self) -> &'static str {
+        "algebra-search"
+    }
+
+    fn description(&self) -> &'static str {
+        "Find subsets of the most frequent species of soups seeded from the generator that are \
+         closed under a rule, and classify their operation tables"
+    }
+
+    fn section<'a>(&self, cfg: &'a config::Experiments) -> &'a config::Ensemble {
+        &cfg.algebra_search.ensemble
+    }
+
+    fn section_mut<'a>(&self, cfg: &'a mut config::Experiments) -> &'a mut config::Ensemble {
+        &mut cfg.algebra_search.ensemble
+    }
+
+    fn parameters(&self, cfg: &config::Config) -> Vec<Parameter> {
+        let cfg = &cfg.experiments.algebra_search;
+        let mut parameters = ensemble_parameters(&cfg.ensemble);
+        parameters.extend([
+            Parameter {
+                name: "top_k",
+                description: "Number of most frequent species to search in each soup",
+                value: cfg.top_k.into(),
+            },
+            Parameter {
+                name: "max_size",
+                description: "Largest closed subset to look for",
+                value: cfg.max_size.into(),
+            },
+        ]);
+        parameters
+    }
+
+    fn run(&self, ctx: &Context) -> io::Result<Box<dyn Report>> {
+        let generator = ctx.config.generator_config.clone();
+        let search = ctx.config.experiments.algebra_search.clone();
+        let results = ctx
+            .ensemble
+            .run(search.ensemble.replicates, move |replicate| {
+                let cfg = &search.ensemble;
+                let sample = generate_sample(&generator, &replicate, cfg.sample_size);
+                let mut soup = seed_soup(cfg, &replicate, sample);
+                soup.simulate_for(cfg.run_limit, false);
+
+                let matrix = InteractionMatrix::from_soup(&soup, search.top_k);
+                find_structures(&matrix, search.max_size)
+                    .into_iter()
+                    .map(|s| StructureRow {
+                        rule: s.rule,
+                        members: s
+                            .members
+                            .iter()
+                            .map(|m| matrix.species()[*m].0.to_string())
+                            .collect(),
+                        table: s.table.rows(),
+                        associative: s.properties.associative,
+                        commutative: s.properties.commutative,
+                        identity: s.properties.identity,
+                        inverses: s.properties.inverses,
+                        isomorphic_to: s.properties.isomorphic_to,
+                    })
+                    .collect::<Vec<_>>()
+            })?;
+
+        let mut soups = results
+            .map(|(replicate, rows)| (replicate.id, rows))
+            .collect::<Vec<_>>();
+        soups.sort_by_key(|(id, _)| *id);
+        Ok(Box::new(AlgebraReport { soups }))
+    }
+}
+
+/// Follow families of structurally related species in soups seeded from the generator
+pub struct FamilyTracking;
+
+/// A family of species of one soup at one poll, see `families::Family`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FamilyRow {
+    pub poll: usize,
+    pub collisions: usize,
+
+    /// Identifier of the family, shared with related families at other polls of the same soup
+    pub family: usize,
+    pub medoid: String,
+
+    /// Number of distinct species in the family
+    pub species: usize,
+
+    /// Total number of copies of the species of the family
+    pub count: u32,
+}
+
+/// The families of each soup at every poll
+#[derive(Debug, Serialize)]
+pub struct FamilyReport {
+    pub soups: Vec<(usize, Vec<FamilyRow>)>,
+}
+
+impl Report for FamilyReport {
+    fn write_csv(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "soup,poll,collisions,family,medoid,species,count")?;
+        for (id, rows) in &self.soups {
+            for row in rows {
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{},{}",
+                    id, row.poll, row.collisions, row.family, row.medoid, row.species, row.count
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_json(&self, out: &mut dyn Write) -> io::Result<()> {
+        write_json(self, out)
+    }
+}
+
+impl Experiment for FamilyTracking {
+    fn name(&self) -> &'static str {
+        "family-tracking"
+    }
+
+    fn description(&self) -> &'static str {
+        "Cluster the most frequent species of soups seeded from the generator into families by \
+         tree edit distance, and follow each family from poll to poll"
+    }
+
+    fn section<'a>(&self, cfg: &'a config::Experiments) -> &'a config::Ensemble {
+        &cfg.family_tracking.ensemble
+    }
+
+    fn section_mut<'a>(&self, cfg: &'a mut config::Experiments) -> &'a mut config::Ensemble {
+        &mut cfg.family_tracking.ensemble
+    }
+
+    fn parameters(&self, cfg: &config::Config) -> Vec<Parameter> {
+        let cfg = &cfg.experiments.family_tracking;
+        let mut parameters = ensemble_parameters(&cfg.ensemble);
+        parameters.extend([
+            Parameter {
+                name: "top_k",
+                description: "Number of most frequent species to cluster at each poll",
+                value: cfg.top_k.into(),
+            },
+            Parameter {
+                name: "max_distance",
+                description: "Largest mean tree edit distance between merged clusters",
+                value: cfg.max_distance.into(),
+            },
+        ]);
+        parameters
+    }
+
+    fn run(&self, ctx: &Context) -> io::Result<Box<dyn Report>> {
+        let generator = ctx.config.generator_config.clone();
+        let tracking = ctx.config.experiments.family_tracking.clone();
+        let results = ctx
+            .ensemble
+            .run(tracking.ensemble.replicates, move |replicate| {
+                let cfg = &tracking.ensemble;
+                let sample = generate_sample(&generator, &replicate, cfg.sample_size);
+                let mut soup = seed_soup(cfg, &replicate, sample);
+                let polls =
+                    soup.simulate_and_poll(cfg.run_limit, cfg.polling_interval, false, |s| {
+                        (
+                            s.collisions(),
+                            s.families(tracking.top_k, tracking.max_distance),
+                        )
+                    });
+
+                let families = polls.iter().map(|(_, f)| f.clone()).collect::<Vec<_>>();
+                let ids = track_families(&families, tracking.max_distance);
+                polls
+                    .iter()
+                    .zip(ids)
+                    .enumerate()
+                    .flat_map(|(poll, ((collisions, families), ids))| {
+                        families.iter().zip(ids).map(move |(f, family)| FamilyRow {
+                            poll,
+                            collisions: *collisions,
+                            family,
+                            medoid: f.medoid.to_string(),
+                            species: f.members.len(),
+                            count: f.count(),
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            })?;
+
+        let mut soups = results
+            .map(|(replicate, rows)| (replicate.id, rows))
+            .collect::<Vec<_>>();
+        soups.sort_by_key(|(id, _)| *id);
+        Ok(Box::new(FamilyReport { soups }))
+    }
+}
+
+/// Follow the most frequent subterms of soups seeded from the generator
+pub struct MotifSeries;
+
+/// A motif of one soup, see `motifs::Motif`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MotifRow {
+    pub rank: usize,
+    pub expr: String,
+    pub size: usize,
+
+    /// Occurrences of the motif at every poll, followed by the final state
+    pub occurrences: Vec<u64>,
+
+    /// Number of species containing the motif at every poll, followed by the final state
+    pub species: Vec<usize>,
+}
+
+/// The most frequent motifs of each soup
+#[derive(Debug, Serialize)]
+pub struct MotifReport {
+    pub soups: Vec<(usize, Vec<MotifRow>)>,
+}
+
+impl Report for MotifReport {
+    fn write_csv(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "soup,rank,expr,size,poll,occurrences,species")?;
+        for (id, rows) in &self.soups {
+            for row in rows {
+                for (poll, (occurrences, species)) in
+                    row.occurrences.iter().zip(&row.species).enumerate()
+                {
+                    writeln!(
+                        out,
+                        "{},{},{},{},{},{},{}",
+                        id, row.rank, row.expr, row.size, poll, occurrences, species
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_json(&self, out: &mut dyn Write) -> io::Result<()> {
+        write_json(self, out)
+    }
+}
+
+impl Experiment for MotifSeries {
+    fn name(&self) -> &'static str {
+        "motif-series"
+    }
+
+    fn description(&self) -> &'static str {
+        "Count the most frequent subterms of soups seeded from the generator, weighted by \
+         abundance, at every poll"
+    }
+
+    fn section<'a>(&self, cfg: &'a config::Experiments) -> &'a config::Ensemble {
+        &cfg.motif_series.ensemble
+    }
+
+    fn section_mut<'a>(&self, cfg: &'a mut config::Experiments) -> &'a mut config::Ensemble {
+        &mut cfg.motif_series.ensemble
+    }
+
+    fn records_abundances(&self) -> bool {
+        true
+    }
+
+    fn parameters(&self, cfg: &config::Config) -> Vec<Parameter> {
+        let cfg = &cfg.experiments.motif_series;
+        let mut parameters = ensemble_parameters(&cfg.ensemble);
+        parameters.extend([
+            Parameter {
+                name: "top_k",
+                description: "Number of most frequent subterms to follow in each soup",
+                value: cfg.top_k.into(),
+            },
+            Parameter {
+                name: "min_size",
+                description: "Smallest number of nodes of a subterm that counts as a motif",
+                value: cfg.min_size.into(),
+            },
+        ]);
+        parameters
+    }
+
+    fn run(&self, ctx: &Context) -> io::Result<Box<dyn Report>> {
+        let generator = ctx.config.generator_config.clone();
+        let motifs = ctx.config.experiments.motif_series.clone();
+        let recorder = ctx.abundances.cloned();
+        let results = ctx
+            .ensemble
+            .run(motifs.ensemble.replicates, move |replicate| {
+                let cfg = &motifs.ensemble;
+                let sample = generate_sample(&generator, &replicate, cfg.sample_size);
+                let mut soup = seed_soup(cfg, &replicate, sample);
+                let tape = soup.simulate_and_record(cfg.run_limit, cfg.polling_interval, false);
+                let tracker = recorder.as_ref().map(|_| tape.abundances());
+                write_abundances(recorder.as_ref(), tracker, &replicate, &cfg.reactor_config);
+
+                let series = tape.motif_series(motifs.top_k, motifs.min_size);
+                series
+                    .motifs
+                    .iter()
+                    .enumerate()
+                    .map(|(rank, motif)| MotifRow {
+                        rank,
+                        expr: motif.expr.to_string(),
+                        size: motif.size,
+                        occurrences: series.occurrences.iter().map(|p| p[rank]).collect(),
+                        species: series.species.iter().map(|p| p[rank]).collect(),
+                    })
+                    .collect::<Vec<_>>()
+            })?;
+
+        let mut soups = results
+            .map(|(replicate, rows)| (replicate.id, rows))
+            .collect::<Vec<_>>();
+        soups.sort_by_key(|(id, _)| *id);
+        Ok(Box::new(MotifReport { soups }))
+    }
+}
+
+/// Look for cycles in the composition of soups seeded from the generator
+pub struct CycleDetection;
+
+/// Composition fingerprint of one soup at each poll, and the cycle it ends in, if any
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CycleRun {
+    pub fingerprints: Vec<u64>,
+    pub cycle: Option<Cycle>,
+}
+
+/// The trajectory of each soup and the cycle it ends in. As CSV, one line per soup, with the
+/// cycle followed by the fingerprint at each poll.
+#[derive(Debug, Serialize)]
+pub struct CycleReport {
+    pub soups: Vec<(usize, CycleRun)>,
+}
+
+impl Report for CycleReport {
+    fn write_csv(&self, out: &mut dyn Write) -> io::Result<()> {
+        let polls = self
+            .soups
+            .iter()
+            .map(|(_, run)| run.fingerprints.len())
+            .max()
+            .unwrap_or(0);
+        write!(out, "soup,polls,period,start,repeats,amplitude")?;
+        for poll in 0..polls {
+            write!(out, ",{}", poll)?;
+        }
+        writeln!(out)?;
+        for (id, run) in &self.soups {
+            write!(out, "{},{}", id, run.fingerprints.len())?;
+            match run.cycle {
+                Some(c) => write!(
+                    out,
+                    ",{},{},{},{}",
+                    c.period, c.start, c.repeats, c.amplitude
+                )?,
+                None => write!(out, ",,,,")?,
+            }
+            for fingerprint in &run.fingerprints {
+                write!(out, ",{}", fingerprint)?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    fn write_json(&self, out: &mut dyn Write) -> io::Result<()> {
+        write_json(self, out)
+    }
+}
+
+impl Experiment for CycleDetection {
+    fn name(&self) -> &'static str {
+        "cycle-detection"
+    }
+
+    fn description(&self) -> &'static str {
+        "Fingerprint the composition of soups seeded from the generator at every poll, and \
+         report the period and amplitude of the cycle each soup ends in"
+    }
+
+    fn section<'a>(&self, cfg: &'a config::Experiments) -> &'a config::Ensemble {
+        &cfg.cycle_detection.ensemble
+    }
+
+    fn section_mut<'a>(&self, cfg: &'a mut config::Experiments) -> &'a mut config::Ensemble {
+        &mut cfg.cycle_detection.ensemble
+    }
+
+    fn records_abundances(&self) -> bool {
+        true
+    }
+
+    fn parameters(&self, cfg: &config::Config) -> Vec<Parameter> {
+        let cfg = &cfg.experiments.cycle_detection;
+        let mut parameters = ensemble_parameters(&cfg.ensemble);
+        parameters.push(Parameter {
+            name: "grain",
+            description: "How finely fingerprints distinguish species counts",
+            value: serde_json::to_value(cfg.grain).unwrap(),
+        });
+        parameters.push(Parameter {
+            name: "min_repeats",
+            description: "Smallest number of complete periods that counts as a cycle",
+            value: serde_json::to_value(cfg.min_repeats).unwrap(),
+        });
+        parameters
+    }
+
+    fn run(&self, ctx: &Context) -> io::Result<Box<dyn Report>> {
+        let generator = ctx.config.generator_config.clone();
+        let cfg = ctx.config.experiments.cycle_detection.ensemble.clone();
+        let grain = ctx.config.experiments.cycle_detection.grain;
+        let min_repeats = ctx.config.experiments.cycle_detection.min_repeats;
+        let observable = ctx.observable;
+        let probes = ctx.config.probe_config.clone();
+        let recorder = ctx.abundances.cloned();
+        let results = ctx.ensemble.run(cfg.replicates, move |replicate| {
+            let sample = generate_sample(&generator, &replicate, cfg.sample_size);
+            let mut soup = seed_soup(&cfg, &replicate, sample);
+            let polls = poll_soup(&mut soup, &cfg, &replicate, recorder.as_ref(), |s| {
+                (s.fingerprint(grain), observable.measure(s, &probes))
+            });
+            let (fingerprints, values): (Vec<_>, Vec<_>) = polls.into_iter().unzip();
+            let cycle = detect_cycle(&fingerprints, &values, min_repeats);
+            CycleRun {
+                fingerprints,
+                cycle,
+            }
+        })?;
+
+        let mut soups = results
+            .map(|(replicate, run)| (replicate.id, run))
+            .collect::<Vec<_>>();
+        soups.sort_by_key(|(id, _)| *id);
+        Ok(Box::new(CycleReport { soups }))
+    }
+}
+
+/// Run perturbed twins of soups seeded from the generator side by side
+pub struct PerturbationSensitivity;
+
+/// Comparison of a soup and its perturbed twin at one poll
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DivergencePoint {
+    /// Number of reactions each copy has run since the twin was perturbed
+    pub reactions: usize,
+    pub bray_curtis: f64,
+
+    /// Cosine similarity of the abundance vectors of the copies
+    pub cosine: f64,
+
+    /// Jensen-Shannon divergence, in bits
+    pub js_divergence: f64,
+
+    /// Overlap of the most frequent species of the copies, see `Soup::top_k_overlap`
+    pub top_k_overlap: f64,
+}
+
+/// Divergence of one soup and its perturbed twin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TwinRun {
+    /// Whether the perturbation changed the twin. Swapping fails if every expression of the
+    /// soup is the same.
+    pub perturbed: bool,
+
+    /// Reactions after which the Bray-Curtis dissimilarity first reached the threshold
+    pub separated_at: Option<usize>,
+    pub points: Vec<DivergencePoint>,
+}
+
+/// The divergence of each soup from its twin
+#[derive(Debug, Serialize)]
+pub struct DivergenceReport {
+    pub soups: Vec<(usize, TwinRun)>,
+}
+
+impl Report for DivergenceReport {
+    fn write_csv(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "soup,perturbed,separated_at,poll,reactions,bray_curtis,cosine,js_divergence,top_k_overlap"
+        )?;
+        for (id, run) in &self.soups {
+            let separated_at = run.separated_at.map(|r| r.to_string()).unwrap_or_default();
+            for (poll, p) in run.points.iter().enumerate() {
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{},{},{},{}",
+                    id,
+                    run.perturbed,
+                    separated_at,
+                    poll,
+                    p.reactions,
+                    p.bray_curtis,
+                    p.cosine,
+                    p.js_divergence,
+                    p.top_k_overlap
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_json(&self, out: &mut dyn Write) -> io::Result<()> {
+        write_json(self, out)
+    }
+}
+
+impl Experiment for PerturbationSensitivity {
+    fn name(&self) -> &'static str {
+        "perturbation-sensitivity"
+    }
+
+    fn description(&self) -> &'static str {
+        "Clone soups seeded from the generator, perturb the clone minimally, and measure how \
+         fast the two copies diverge"
+    }
+
+    fn section<'a>(&self, cfg: &'a config::Experiments) -> &'a config::Ensemble {
+        &cfg.perturbation.ensemble
+    }
+
+    fn section_mut<'a>(&self, cfg: &'a mut config::Experiments) -> &'a mut config::Ensemble {
+        &mut cfg.perturbation.ensemble
+    }
+
+    fn parameters(&self, cfg: &config::Config) -> Vec<Parameter> {
+        let cfg = &cfg.experiments.perturbation;
+        let mut parameters = ensemble_parameters(&cfg.ensemble);
+        parameters.extend([
+            Parameter {
+                name: "warmup",
+                description: "Number of reactions to run before cloning the soup",
+                value: cfg.warmup.into(),
+            },
+            Parameter {
+                name: "perturbation",
+                description: "How the clone is perturbed, `Swap` or `Reseed`",
+                value: serde_json::to_value(cfg.perturbation).unwrap(),
+            },
+            Parameter {
+                name: "top_k",
+                description: "Number of most frequent species compared between the copies",
+                value: cfg.top_k.into(),
+            },
+            Parameter {
+                name: "threshold",
+                description: "Bray-Curtis dissimilarity from which the copies count as separated",
+                value: cfg.threshold.into(),
+            },
+        ]);
+        parameters
+    }
+
+    fn run(&self, ctx: &Context) -> io::Result<Box<dyn Report>> {
+        let generator = ctx.config.generator_config.clone();
+        let perturbation = ctx.config.experiments.perturbation.clone();
+        let results = ctx
+            .ensemble
+            .run(perturbation.ensemble.replicates, move |replicate| {
+                let cfg = &perturbation.ensemble;
+                let sample = generate_sample(&generator, &replicate, cfg.sample_size);
+                let mut soup = seed_soup(cfg, &replicate, sample);
+                soup.simulate_for(perturbation.warmup, false);
+
+                let mut twin = soup.clone();
+                let seed = replicate.perturbation_seed().get();
+                let perturbed = match perturbation.perturbation {
+                    config::Perturb::Swap => twin.replace_one(&mut ChaCha8Rng::from_seed(seed)),
+                    config::Perturb::Reseed => {
+                        twin.reseed(seed);
+                        true
+                    }
+                };
+
+                let mut points = Vec::new();
+                for poll in 0..cfg.run_limit / cfg.polling_interval {
+                    points.push(DivergencePoint {
+                        reactions: poll * cfg.polling_interval,
+                        bray_curtis: soup.bray_curtis(&twin),
+                        cosine: soup.cosine_similarity(&twin),
+                        js_divergence: soup.js_divergence(&twin, Base::Bits),
+                        top_k_overlap: soup.top_k_overlap(&twin, perturbation.top_k),
+                    });
+                    soup.simulate_for(cfg.polling_interval, false);
+                    twin.simulate_for(cfg.polling_interval, false);
+                }
+                let separated_at = points
+                    .iter()
+                    .find(|p| p.bray_curtis >= perturbation.threshold)
+                    .map(|p| p.reactions);
+                TwinRun {
+                    perturbed,
+                    separated_at,
+                    points,
+                }
+            })?;
+
+        // Summaries follow the Bray-Curtis dissimilarity across the ensemble
+        if ctx.summary {
+            let mut aggregator = SeriesAggregator::new();
+            for (_, run) in results {
+                aggregator.push(run.points.iter().map(|p| p.bray_curtis as f32));
+            }
+            let options = SummaryOptions::new();
+            let summaries = aggregator.summarize(&options);
+            return Ok(Box::new(SummaryReport { options, summaries }));
+        }
+        let mut soups = results
+            .map(|(replicate, run)| (replicate.id, run))
+            .collect::<Vec<_>>();
+        soups.sort_by_key(|(id, _)| *id);
+        Ok(Box::new(DivergenceReport { soups }))
+    }
+}
+
+/// Record the distribution of complexity metrics in soups seeded from the generator
+pub struct ComplexityHistograms;
+
+/// Distribution of each metric at every poll of each soup
+#[derive(Debug, Serialize)]
+pub struct ComplexityReport {
+    pub metrics: Vec<Metric>,
+
+    /// `soups[i].1[poll][j]` is the distribution of `metrics[j]` at that poll
+    pub soups: Vec<(usize, Vec<Vec<Distribution>>)>,
+}
+
+impl Report for ComplexityReport {
+    fn write_csv(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "soup,poll,metric,value,count")?;
+        for (id, polls) in &self.soups {
+            for (poll, distributions) in polls.iter().enumerate() {
+                for (metric, d) in self.metrics.iter().zip(distributions) {
+                    for (value, count) in &d.histogram {
+                        writeln!(out, "{},{},{},{},{}", id, poll, metric, value, count)?;
+                    }
+                    if d.undefined > 0 {
+                        writeln!(out, "{},{},{},undefined,{}", id, poll, metric, d.undefined)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_json(&self, out: &mut dyn Write) -> io::Result<()> {
+        write_json(self, out)
+    }
+}
+
+impl Experiment for ComplexityHistograms {
+    fn name(&self) -> &'static str {
+        "complexity-histograms"
+    }
+
+    fn description(&self) -> &'static str {
+        "Record histograms of complexity metrics over the expressions of soups seeded from the \
+         generator at every poll"
+    }
+
+    fn section<'a>(&self, cfg: &'a config::Experiments) -> &'a config::Ensemble {
+        &cfg.complexity_histograms.ensemble
+    }
+
+    fn section_mut<'a>(&self, cfg: &'a mut config::Experiments) -> &'a mut config::Ensemble {
+        &mut cfg.complexity_histograms.ensemble
+    }
+
+    fn records_abundances(&self) -> bool {
+        true
+    }
+
+    fn parameters(&self, cfg: &config::Config) -> Vec<Parameter> {
+        let cfg = &cfg.experiments.complexity_histograms;
+        let mut parameters = ensemble_parameters(&cfg.ensemble);
+        parameters.push(Parameter {
+            name: "metrics",
+            description: "Complexity metrics to record",
+            value: serde_json::to_value(&cfg.metrics).unwrap(),
+        });
+        parameters
+    }
+
+    fn run(&self, ctx: &Context) -> io::Result<Box<dyn Report>> {
+        let generator = ctx.config.generator_config.clone();
+        let cfg = ctx
+            .config
+            .experiments
+            .complexity_histograms
+            .ensemble
+            .clone();
+        let metrics = ctx.config.experiments.complexity_histograms.metrics.clone();
+        let job_metrics = metrics.clone();
+        let recorder = ctx.abundances.cloned();
+        let results = ctx.ensemble.run(cfg.replicates, move |replicate| {
+            let sample = generate_sample(&generator, &replicate, cfg.sample_size);
+            let mut soup = seed_soup(&cfg, &replicate, sample);
+            poll_soup(&mut soup, &cfg, &replicate, recorder.as_ref(), |s| {
+                job_metrics
+                    .iter()
+                    .map(|m| s.complexity(*m))
+                    .collect::<Vec<_>>()
+            })
+        })?;
+
+        let mut soups = results
+            .map(|(replicate, polls)| (replicate.id, polls))
+            .collect::<Vec<_>>();
+        soups.sort_by_key(|(id, _)| *id);
+        Ok(Box::new(ComplexityReport { metrics, soups }))
+    }
+}
+
+/// Track the compressed size of soups seeded from the generator
+pub struct CompressionSeries;
+
+/// Compression of each soup at every poll
+#[derive(Debug, Serialize)]
+pub struct CompressionReport {
+    pub soups: Vec<(usize, Vec<CompressionPoint>)>,
+}
+
+impl Report for CompressionReport {
+    fn write_csv(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "soup,poll,raw_size,compressed_size,ncd")?;
+        for (id, points) in &self.soups {
+            for (poll, point) in points.iter().enumerate() {
+                writeln!(
+                    out,
+                    "{},{},{},{},{}",
+                    id,
+                    poll,
+                    point.raw_size,
+                    point.compressed_size,
+                    point.ncd.map_or(String::new(), |d| d.to_string())
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_json(&self, out: &mut dyn Write) -> io::Result<()> {
+        write_json(self, out)
+    }
+}
+
+impl Experiment for CompressionSeries {
+    fn name(&self) -> &'static str {
+        "compression-series"
+    }
+
+    fn description(&self) -> &'static str {
+        "Poll the compressed size of soups seeded from the generator, and the normalized \
+         compression distance between successive polls"
+    }
+
+    fn section<'a>(&self, cfg: &'a config::Experiments) -> &'a config::Ensemble {
+        &cfg.compression_series
+    }
+
+    fn section_mut<'a>(&self, cfg: &'a mut config::Experiments) -> &'a mut config::Ensemble {
+        &mut cfg.compression_series
+    }
+
+    fn records_abundances(&self) -> bool {
+        true
+    }
+
+    fn run(&self, ctx: &Context) -> io::Result<Box<dyn Report>> {
+        let generator = ctx.config.generator_config.clone();
+        let cfg = ctx.config.experiments.compression_series.clone();
+        let recorder = ctx.abundances.cloned();
+        let results = ctx.ensemble.run(cfg.replicates, move |replicate| {
+            let sample = generate_sample(&generator, &replicate, cfg.sample_size);
+            let mut soup = seed_soup(&cfg, &replicate, sample);
+            let mut tracker = CompressionTracker::new();
+            poll_soup(&mut soup, &cfg, &replicate, recorder.as_ref(), |s| {
+                tracker.poll(s.species_lines())
+            })
+        })?;
+
+        let mut soups = results
+            .map(|(replicate, points)| (replicate.id, points))
+            .collect::<Vec<_>>();
+        soups.sort_by_key(|(id, _)| *id);
+        Ok(Box::new(CompressionReport { soups }))
+    }
+}
+
+fn simulate_soup(
+    sample: impl Iterator<Item = Term>,
+    cfg: &config::Ensemble,
+    replicate: Replicate,
+) -> (f32, f32) {
+    let mut soup = seed_soup(cfg, &replicate, sample);
+    let n_successes = soup.simulate_for(cfg.run_limit, false);
+    let failure_rate = 1f32 - n_successes as f32 / cfg.run_limit as f32;
+    (soup.population_entropy(), failure_rate)
+}
+
+/// Polled series of soups seeded from the generator
+pub struct EntropySeries;
+
+impl Experiment for EntropySeries {
+    fn name(&self) -> &'static str {
+        "entropy-series"
+    }
+
+    fn description(&self) -> &'static str {
+        "Poll the observable in an ensemble of soups seeded from the generator"
+    }
+
+    fn section<'a>(&self, cfg: &'a config::Experiments) -> &'a config::Ensemble {
+        &cfg.entropy_series
+    }
+
+    fn section_mut<'a>(&self, cfg: &'a mut config::Experiments) -> &'a mut config::Ensemble {
+        &mut cfg.entropy_series
+    }
+
+    fn records_abundances(&self) -> bool {
+        true
+    }
+
+    fn parameters(&self, cfg: &config::Config) -> Vec<Parameter> {
+        stopping_parameters(&cfg.experiments.entropy_series)
+    }
+
+    fn run(&self, ctx: &Context) -> io::Result<Box<dyn Report>> {
+        let generator = ctx.config.generator_config.clone();
+        let cfg = ctx.config.experiments.entropy_series.clone();
+        let polls = cfg.run_limit / cfg.polling_interval;
+        let observable = ctx.observable;
+        let probes = ctx.config.probe_config.clone();
+        let recorder = ctx.abundances.cloned();
+        let results = ctx.ensemble.run(cfg.replicates, move |replicate| {
+            let sample = generate_sample(&generator, &replicate, cfg.sample_size);
+            simulate_soup_and_poll(
+                sample.into_iter(),
+                &cfg,
+                replicate,
+                observable,
+                &probes,
+                recorder.as_ref(),
+            )
+        })?;
+        Ok(series_report(results, polls, ctx.summary))
+    }
+}
+
+/// Final entropy and failure rate of soups seeded from the generator
+pub struct EntropyTest;
+
+/// Final entropy and failure rate of each soup
+#[derive(Debug, Serialize)]
+pub struct EntropyReport {
+    /// Replicate, final population entropy, and fraction of failed reactions
+    pub soups: Vec<(usize, f32, f32)>,
+}
+
+impl Report for EntropyReport {
+    fn write_csv(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "soup,entropy,failure_rate")?;
+        for (id, entropy, failure_rate) in &self.soups {
+            writeln!(out, "{},{},{}", id, entropy, failure_rate)?;
+        }
+        Ok(())
+    }
+
+    fn write_json(&self, out: &mut dyn Write) -> io::Result<()> {
+        write_json(self, out)
+    }
+}
+
+impl Experiment for EntropyTest {
+    fn name(&self) -> &'static str {
+        "entropy-test"
+    }
+
+    fn description(&self) -> &'static str {
+        "Final entropy and failure rate of soups seeded from the generator, with a histogram \
+         of entropies in test.png"
+    }
+
+    fn section<'a>(&self, cfg: &'a config::Experiments) -> &'a config::Ensemble {
+        &cfg.entropy_test
+    }
+
+    fn section_mut<'a>(&self, cfg: &'a mut config::Experiments) -> &'a mut config::Ensemble {
+        &mut cfg.entropy_test
+    }
+
+    fn run(&self, ctx: &Context) -> io::Result<Box<dyn Report>> {
+        let generator = ctx.config.generator_config.clone();
+        let cfg = ctx.config.experiments.entropy_test.clone();
+        let results = ctx.ensemble.run(cfg.replicates, move |replicate| {
+            let sample = generate_sample(&generator, &replicate, cfg.sample_size);
+            simulate_soup(sample.into_iter(), &cfg, replicate)
+        })?;
+
+        let mut soups = results
+            .map(|(replicate, (entropy, failure_rate))| (replicate.id, entropy, failure_rate))
+            .collect::<Vec<_>>();
+        soups.sort_by_key(|(id, _, _)| *id);
+
+        let data = soups.iter().map(|(_, e, _)| *e).collect::<Vec<_>>();
+        plot_histogram(&data).unwrap();
+        Ok(Box::new(EntropyReport { soups }))
+    }
+}
+
+/// Final entropy of small soups seeded from the generator
+pub struct SyncEntropyTest;
+
+impl Experiment for SyncEntropyTest {
+    fn name(&self) -> &'static str {
+        "sync-entropy-test"
+    }
+
+    fn description(&self) -> &'static str {
+        "Final entropy of small soups seeded from the generator"
+    }
+
+    fn section<'a>(&self, cfg: &'a config::Experiments) -> &'a config::Ensemble {
+        &cfg.sync_entropy_test
+    }
+
+    fn section_mut<'a>(&self, cfg: &'a mut config::Experiments) -> &'a mut config::Ensemble {
+        &mut cfg.sync_entropy_test
+    }
+
+    fn run(&self, ctx: &Context) -> io::Result<Box<dyn Report>> {
+        let generator = ctx.config.generator_config.clone();
+        let cfg = ctx.config.experiments.sync_entropy_test.clone();
+        let results = ctx.ensemble.run(cfg.replicates, move |replicate| {
+            let sample = generate_sample(&generator, &replicate, cfg.sample_size);
+            simulate_soup(sample.into_iter(), &cfg, replicate)
+        })?;
+
+        let mut soups = results
+            .map(|(replicate, (entropy, failure_rate))| (replicate.id, entropy, failure_rate))
+            .collect::<Vec<_>>();
+        soups.sort_by_key(|(id, _, _)| *id);
+        Ok(Box::new(EntropyReport { soups }))
     }
 }
 
@@ -0,0 +1,277 @@
+use lambda_calculus::Term;
+
+use crate::soup::Soup;
+
+/// Label of a node of an expression, seen as an ordered tree. Variables keep their De Bruijn
+/// index, so relabeling `Var(1)` to `Var(2)` costs as much as any other relabeling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Label {
+    Var(usize),
+    Abs,
+    App,
+}
+
+/// Nodes of an expression in postorder, with the index of the leftmost leaf below each node.
+struct Tree {
+    labels: Vec<Label>,
+    leftmost: Vec<usize>,
+}
+
+impl Tree {
+    fn new(expr: &Term) -> Self {
+        let mut tree = Tree {
+            labels: Vec::new(),
+            leftmost: Vec::new(),
+        };
+        tree.visit(expr);
+        tree
+    }
+
+    /// Append the nodes of `expr` in postorder and return the index of its root.
+    fn visit(&mut self, expr: &Term) -> usize {
+        let (label, leftmost) = match expr {
+            Term::Var(i) => (Label::Var(*i), self.labels.len()),
+            Term::Abs(body) => {
+                let body = self.visit(body);
+                (Label::Abs, self.leftmost[body])
+            }
+            Term::App(app) => {
+                let left = self.visit(&app.0);
+                self.visit(&app.1);
+                (Label::App, self.leftmost[left])
+            }
+        };
+        self.labels.push(label);
+        self.leftmost.push(leftmost);
+        self.labels.len() - 1
+    }
+
+    fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// The root, and nodes that are not the leftmost child of their parent, in increasing order.
+    /// These are the roots of the subtrees the edit distance algorithm iterates over.
+    fn keyroots(&self) -> Vec<usize> {
+        (0..self.len())
+            .filter(|i| (i + 1..self.len()).all(|j| self.leftmost[j] != self.leftmost[*i]))
+            .collect()
+    }
+}
+
+/// Tree edit distance between two expressions, the smallest number of node insertions,
+/// deletions and relabelings that turns one De Bruijn tree into the other. Uses the algorithm of
+/// Zhang and Shasha, which takes time O(n1 n2 min(d1, l1) min(d2, l2)) for trees of `n` nodes,
+/// depth `d` and `l` leaves: between the product of the sizes and its square.
+pub fn tree_edit_distance(a: &Term, b: &Term) -> usize {
+    let (a, b) = (Tree::new(a), Tree::new(b));
+    let mut tree_distance = vec![vec![0; b.len()]; a.len()];
+    for i in a.keyroots() {
+        for j in b.keyroots() {
+            let (li, lj) = (a.leftmost[i], b.leftmost[j]);
+            let (w, h) = (i - li + 2, j - lj + 2);
+
+            // Distance between the forests made of the first `x` and `y` nodes of each subtree
+            let mut forest = vec![vec![0; h]; w];
+            for x in 1..w {
+                forest[x][0] = forest[x - 1][0] + 1;
+            }
+            for y in 1..h {
+                forest[0][y] = forest[0][y - 1] + 1;
+            }
+            for x in 1..w {
+                for y in 1..h {
+                    let (di, dj) = (li + x - 1, lj + y - 1);
+                    let edit = (forest[x - 1][y] + 1).min(forest[x][y - 1] + 1);
+                    if a.leftmost[di] == li && b.leftmost[dj] == lj {
+                        let relabel = (a.labels[di] != b.labels[dj]) as usize;
+                        forest[x][y] = edit.min(forest[x - 1][y - 1] + relabel);
+                        tree_distance[di][dj] = forest[x][y];
+                    } else {
+                        let (px, py) = (a.leftmost[di] - li, b.leftmost[dj] - lj);
+                        forest[x][y] = edit.min(forest[px][py] + tree_distance[di][dj]);
+                    }
+                }
+            }
+        }
+    }
+    tree_distance[a.len() - 1][b.len() - 1]
+}
+
+/// A family of structurally related species.
+#[derive(Debug, Clone)]
+pub struct Family {
+    /// The member with the smallest total distance to the other members, weighted by their
+    /// number of copies
+    pub medoid: Term,
+
+    /// Species of the family, with their number of copies, in decreasing order of frequency
+    pub members: Vec<(Term, u32)>,
+}
+
+impl Family {
+    /// Total number of copies of the members of the family.
+    pub fn count(&self) -> u32 {
+        self.members.iter().map(|(_, c)| c).sum()
+    }
+}
+
+/// Group `species` into families by average linkage hierarchical clustering on the tree edit
+/// distance. Clusters are merged, closest first, while the mean distance between their members
+/// is at most `max_distance`. Families are returned in decreasing order of total count.
+pub fn cluster(species: &[(Term, u32)], max_distance: f64) -> Vec<Family> {
+    let n = species.len();
+    let mut distance = vec![vec![0; n]; n];
+    for i in 0..n {
+        for j in 0..i {
+            let d = tree_edit_distance(&species[i].0, &species[j].0);
+            distance[i][j] = d;
+            distance[j][i] = d;
+        }
+    }
+
+    let linkage = |a: &[usize], b: &[usize]| {
+        let total = a
+            .iter()
+            .flat_map(|i| b.iter().map(|j| distance[*i][*j]))
+            .sum::<usize>();
+        total as f64 / (a.len() * b.len()) as f64
+    };
+
+    let mut clusters = (0..n).map(|i| vec![i]).collect::<Vec<_>>();
+    loop {
+        let mut closest: Option<(f64, usize, usize)> = None;
+        for a in 0..clusters.len() {
+            for b in 0..a {
+                let d = linkage(&clusters[a], &clusters[b]);
+                if closest.map_or(true, |(c, _, _)| d < c) {
+                    closest = Some((d, a, b));
+                }
+            }
+        }
+        match closest {
+            Some((d, a, b)) if d <= max_distance => {
+                let merged = clusters.swap_remove(a);
+                clusters[b].extend(merged);
+            }
+            _ => break,
+        }
+    }
+
+    let mut families = clusters
+        .into_iter()
+        .map(|mut members| {
+            let cost = |i: usize| {
+                members
+                    .iter()
+                    .map(|j| distance[i][*j] * species[*j].1 as usize)
+                    .sum::<usize>()
+            };
+            let medoid = *members.iter().min_by_key(|i| cost(**i)).unwrap();
+            members.sort_by_key(|i| std::cmp::Reverse(species[*i].1));
+            Family {
+                medoid: species[medoid].0.clone(),
+                members: members.iter().map(|i| species[*i].clone()).collect(),
+            }
+        })
+        .collect::<Vec<_>>();
+    families.sort_by_key(|f| std::cmp::Reverse(f.count()));
+    families
+}
+
+/// Follow families across a series of clusterings. Each family is given the identifier of the
+/// closest family seen at an earlier poll, if their medoids are at most `max_distance` apart
+/// and no larger family of the same poll has claimed it, and a new identifier otherwise.
+/// Returns the identifiers of the families of each poll, in the same order.
+pub fn track_families(polls: &[Vec<Family>], max_distance: f64) -> Vec<Vec<usize>> {
+    let mut lineages: Vec<Term> = Vec::new();
+    let mut ids = Vec::with_capacity(polls.len());
+    for families in polls {
+        let mut claimed = vec![false; lineages.len()];
+        let mut poll_ids = Vec::with_capacity(families.len());
+        for family in families {
+            let closest = lineages
+                .iter()
+                .enumerate()
+                .filter(|(id, _)| !claimed[*id])
+                .map(|(id, medoid)| (tree_edit_distance(medoid, &family.medoid), id))
+                .min();
+            let id = match closest {
+                Some((d, id)) if d as f64 <= max_distance => {
+                    lineages[id] = family.medoid.clone();
+                    id
+                }
+                _ => {
+                    lineages.push(family.medoid.clone());
+                    claimed.push(false);
+                    lineages.len() - 1
+                }
+            };
+            claimed[id] = true;
+            poll_ids.push(id);
+        }
+        ids.push(poll_ids);
+    }
+    ids
+}
+
+impl Soup {
+    /// Cluster the `k` most frequent species of the soup into families, see `cluster`.
+    pub fn families(&self, k: usize, max_distance: f64) -> Vec<Family> {
+        let counts = self.expression_counts();
+        let species = self
+            .k_most_frequent_exprs(k)
+            .into_iter()
+            .map(|t| {
+                let count = counts[&t];
+                (t, count)
+            })
+            .collect::<Vec<_>>();
+        cluster(&species, max_distance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lambda_calculus::{abs, app, Var};
+
+    #[test]
+    fn distance_to_itself_is_zero() {
+        let s = abs(abs(abs(app(app(Var(3), Var(1)), app(Var(2), Var(1))))));
+        assert_eq!(tree_edit_distance(&s, &s), 0);
+        assert_eq!(tree_edit_distance(&Var(1), &Var(1)), 0);
+    }
+
+    #[test]
+    fn relabel_costs_one() {
+        // λλ.2 and λλ.1 differ only in the label of their leaf
+        assert_eq!(tree_edit_distance(&abs(abs(Var(2))), &abs(abs(Var(1)))), 1);
+        // The right operand of the application changes from 2 to 1
+        let a = abs(app(Var(1), Var(2)));
+        let b = abs(app(Var(1), Var(1)));
+        assert_eq!(tree_edit_distance(&a, &b), 1);
+    }
+
+    #[test]
+    fn insertions_and_deletions() {
+        // Insert one abstraction
+        assert_eq!(tree_edit_distance(&abs(Var(1)), &abs(abs(Var(1)))), 1);
+        // Delete the application and one of its variables
+        assert_eq!(tree_edit_distance(&app(Var(1), Var(2)), &Var(2)), 2);
+    }
+
+    #[test]
+    fn distance_is_symmetric() {
+        let identity = abs(Var(1));
+        let omega = abs(app(Var(1), Var(1)));
+        let k = abs(abs(Var(2)));
+        // λ.1 to λ.1 1: insert the application and a variable
+        assert_eq!(tree_edit_distance(&identity, &omega), 2);
+        assert_eq!(tree_edit_distance(&omega, &identity), 2);
+        assert_eq!(
+            tree_edit_distance(&k, &omega),
+            tree_edit_distance(&omega, &k)
+        );
+    }
+}
@@ -142,6 +142,22 @@ impl BTreeGen {
     }
 }
 
+/// Generate `n` expressions with the generator described by `cfg`.
+pub fn generate_from_config(cfg: &config::Generator, n: usize) -> Vec<Term> {
+    match cfg {
+        config::Generator::BTree(gen_cfg) => {
+            let mut gen = BTreeGen::from_config(gen_cfg);
+            gen.generate_n(n)
+        }
+        config::Generator::Fontana(gen_cfg) => {
+            let gen = FontanaGen::from_config(gen_cfg);
+            std::iter::from_fn(move || gen.generate())
+                .take(n)
+                .collect::<Vec<Term>>()
+        }
+    }
+}
+
 pub struct FontanaGen {
     abs_range: (f64, f64),
     app_range: (f64, f64),
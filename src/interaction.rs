@@ -0,0 +1,167 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+use std::path::Path;
+
+use lambda_calculus::Term;
+use plotters::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::soup::Soup;
+
+/// The product of a collision between two species of an interaction matrix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Product {
+    /// The product is isomorphic to the species with this index
+    Species(usize),
+
+    /// The product is not one of the species of the matrix
+    Outside,
+
+    /// The collision failed, for example because it exceeded the reduction limit
+    Failed,
+}
+
+/// Products of every ordered pair of the most frequent species of a soup, under every reaction
+/// rule, using the same reduction limits as a reaction. Copies of parents are kept even if the
+/// reactor discards them.
+#[derive(Debug, Clone)]
+pub struct InteractionMatrix {
+    species: Vec<(Term, u32)>,
+    n_rules: usize,
+    products: Vec<Product>,
+}
+
+impl InteractionMatrix {
+    /// Compute the interaction matrix of the `k` most frequent species of `soup`. Species are
+    /// indexed in decreasing order of frequency.
+    pub fn from_soup(soup: &Soup, k: usize) -> Self {
+        let counts = soup.expression_counts();
+        let species = soup
+            .k_most_frequent_exprs(k)
+            .into_iter()
+            .map(|t| {
+                let count = counts[&t];
+                (t, count)
+            })
+            .collect::<Vec<_>>();
+
+        let mut n_rules = 0;
+        let mut products = Vec::with_capacity(species.len() * species.len());
+        for (left, _) in &species {
+            for (right, _) in &species {
+                let results = soup.products(left, right);
+                n_rules = results.len();
+                products.extend(results.into_iter().map(|r| {
+                    match r {
+                        Ok(p) => species
+                            .iter()
+                            .position(|(s, _)| s.is_isomorphic_to(&p))
+                            .map_or(Product::Outside, Product::Species),
+                        Err(_) => Product::Failed,
+                    }
+                }));
+            }
+        }
+
+        InteractionMatrix {
+            species,
+            n_rules,
+            products,
+        }
+    }
+
+    /// The species of the matrix, with their number of copies in the soup.
+    pub fn species(&self) -> &[(Term, u32)] {
+        &self.species
+    }
+
+    /// Number of species in the matrix.
+    pub fn len(&self) -> usize {
+        self.species.len()
+    }
+
+    /// Number of reaction rules.
+    pub fn n_rules(&self) -> usize {
+        self.n_rules
+    }
+
+    /// Product of `left` and `right` under the rule with index `rule`.
+    pub fn get(&self, left: usize, right: usize, rule: usize) -> Product {
+        self.products[(left * self.len() + right) * self.n_rules + rule]
+    }
+
+    /// Products of `left` and `right` under every rule.
+    pub fn products(&self, left: usize, right: usize) -> impl Iterator<Item = Product> + '_ {
+        (0..self.n_rules).map(move |rule| self.get(left, right, rule))
+    }
+
+    /// Write the matrix as CSV, with one row for each pair of species and rule.
+    pub fn write_csv(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "rule,left,right,product,left_expr,right_expr")?;
+        for left in 0..self.len() {
+            for right in 0..self.len() {
+                for rule in 0..self.n_rules {
+                    writeln!(
+                        out,
+                        "{},{},{},{},{},{}",
+                        rule,
+                        left,
+                        right,
+                        self.get(left, right, rule),
+                        self.species[left].0,
+                        self.species[right].0
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw the matrix of the rule with index `rule` as a heatmap at `path`. Each product has
+    /// its own color, products outside the matrix are grey and failed collisions are black.
+    pub fn plot_heatmap(&self, path: impl AsRef<Path>, rule: usize) -> Result<(), Box<dyn Error>> {
+        let root = BitMapBackend::new(path.as_ref(), (1000, 1000)).into_drawing_area();
+        root.fill(&WHITE)?;
+
+        let k = self.len() as u32;
+        let mut chart = ChartBuilder::on(&root)
+            .x_label_area_size(35)
+            .y_label_area_size(40)
+            .margin(5)
+            .caption(format!("Interactions, rule {}", rule), ("sans-serif", 50.0))
+            .build_cartesian_2d(0u32..k, k..0u32)?;
+
+        chart
+            .configure_mesh()
+            .disable_mesh()
+            .x_desc("Right")
+            .y_desc("Left")
+            .axis_desc_style(("sans-serif", 15))
+            .draw()?;
+
+        chart.draw_series((0..k).flat_map(|left| {
+            (0..k).map(move |right| {
+                let color = match self.get(left as usize, right as usize, rule) {
+                    Product::Species(i) => HSLColor(i as f64 / k.max(1) as f64, 0.7, 0.5).filled(),
+                    Product::Outside => RGBColor(200, 200, 200).filled(),
+                    Product::Failed => BLACK.filled(),
+                };
+                Rectangle::new([(right, left), (right + 1, left + 1)], color)
+            })
+        }))?;
+
+        root.present()?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for Product {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Product::Species(i) => write!(f, "{}", i),
+            Product::Outside => write!(f, "outside"),
+            Product::Failed => write!(f, "failed"),
+        }
+    }
+}
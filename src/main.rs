@@ -1,40 +1,85 @@
-use clap::{Parser, ValueEnum};
-use futures::executor::block_on;
+use clap::Parser;
 use generators::BTreeGen;
 use lambda_calculus::*;
 use std::fs::{read_to_string, File};
 use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+/// Species abundance records
+mod abundance;
+
+/// Algebraic structures in reaction tables
+mod algebra;
+
+/// Assembly index of expressions
+mod assembly;
 
 /// Simulation analysis
 mod analysis;
 
+/// Behavioral equivalence of expressions
+mod behavior;
+
+/// Names of known expressions
+mod classifier;
+
+/// Recurrences in soup trajectories
+mod cycles;
+
+/// Complexity of expressions
+mod complexity;
+
+/// Compression-based complexity of soups
+mod compression;
+
 /// Global configuration
 mod config;
 
+/// Parallel ensemble runner
+mod ensemble;
+
+/// Structural distance and families of species
+mod families;
+
+/// Novelty tracking
+mod novelty;
+
 /// Random expression generators
 mod generators;
 
+/// Diversity indices
+mod diversity;
+
+/// Ensemble statistics
+mod statistics;
+
+/// Self-replicator detection
+mod replication;
+
+/// Reaction tables between species
+mod interaction;
+
+/// Similarity and divergence between soups
+mod similarity;
+
+/// Frequent subterms of a soup
+mod motifs;
+
 /// Main AlChemy simulation module
 mod soup;
 
 /// Experimental stuff
 mod experiments;
 
+/// Early stopping of long runs
+mod stopping;
+
+/// Parameter sweeps over configuration fields
+mod sweep;
+
 /// Utilities
 mod utils;
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
-pub enum Experiment {
-    XorsetStability,
-    XorsetSearch,
-    AdditionSearch,
-    SyncEntropyTest,
-    EntropyTest,
-    EntropySeries,
-    SampleSimulate,
-    SampleScan,
-}
-
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Cli {
@@ -53,6 +98,16 @@ struct Cli {
     #[arg(short, long)]
     run_limit: Option<usize>,
 
+    /// Number of soups in an experiment ensemble. If set, this flag overwrites the `replicates`
+    /// option of the experiment's configuration section.
+    #[arg(long)]
+    replicates: Option<usize>,
+
+    /// Number of expressions each soup starts with. If set, this flag overwrites the
+    /// `sample_size` configuration option, and the option of the experiment's section.
+    #[arg(long)]
+    sample_size: Option<usize>,
+
     /// Explicit path to configuration file
     #[arg(short, long)]
     config_file: Option<String>,
@@ -61,9 +116,79 @@ struct Cli {
     #[arg(long)]
     dump_config: bool,
 
-    /// Run an experiment and exit
+    /// Run the experiment with this name and exit
     #[arg(short, long)]
-    experiment: Option<Experiment>,
+    experiment: Option<String>,
+
+    /// List all experiments and exit
+    #[arg(long)]
+    list_experiments: bool,
+
+    /// Print the description and parameters of the experiment with this name and exit
+    #[arg(long)]
+    describe_experiment: Option<String>,
+
+    /// Output format of experiment results and summaries
+    #[arg(long, value_enum, default_value_t = statistics::Format::Csv)]
+    format: statistics::Format,
+
+    /// Number of worker threads used to run experiment ensembles. Defaults to one worker per
+    /// available core.
+    #[arg(short, long, default_value_t = 0)]
+    workers: usize,
+
+    /// Store the result of each experiment replicate in this directory. Rerunning the same
+    /// experiment with the same directory skips the replicates that have already finished.
+    #[arg(long)]
+    results_dir: Option<String>,
+
+    /// Identifier of an experiment run. Replicate seeds are derived from it. Defaults to the run
+    /// id stored in `results_dir`, or to the name of `results_dir` for a new run.
+    #[arg(long)]
+    run_id: Option<String>,
+
+    /// Quantity to measure at each poll, both for polled simulations and for experiments that
+    /// produce a series per soup. One of `entropy`, `species-count` (or `richness`),
+    /// `failure-rate`, `behavioral-classes`, `shannon-bits`, `shannon-nats`, `simpson`,
+    /// `gini-simpson`, `hill:<q>`, `evenness`, `berger-parker`, `novelty`,
+    /// `cumulative-distinct`, `novel-total`, `mean:<metric>`, `max:<metric>`, `assembly`,
+    /// `assembly-index`, `compressed-size` and `compression-ratio`. `novelty`,
+    /// `cumulative-distinct` and `novel-total` turn on `reactor_config.track_novelty`, also in
+    /// every run of a sweep. Complexity metrics are `size`, `abstractions`, `applications`,
+    /// `redexes`, `binder-depth`, `free-variables`, `normal-form-size` and `reduction-steps`.
+    #[arg(long, default_value_t = analysis::Observable::Entropy)]
+    observable: analysis::Observable,
+
+    /// Write the number of copies of each species at each poll of a simulation to
+    /// `abundances.csv` in this directory, as sparse `poll,species,count` records, and the
+    /// species dictionary to `species.csv`. Without a polling interval, only the final soup is
+    /// recorded. Species are filtered by `abundance_config`. Experiments that poll a series of
+    /// each soup, and sweeps, write each replicate to its own `replicate-<id>` directory in this
+    /// directory instead. Replicates loaded from `--results-dir` are not run again, so they are
+    /// not written.
+    #[arg(long)]
+    abundances: Option<String>,
+
+    /// After a polled simulation, look for a cycle in the composition of the soup at this grain,
+    /// and print its period and amplitude on `observable` to stderr.
+    #[arg(long, value_enum)]
+    detect_cycle: Option<cycles::Grain>,
+
+    /// Smallest number of complete periods that counts as a cycle, both for `--detect-cycle` and
+    /// for the `cycle-detection` experiment. If set, this flag overwrites the
+    /// `experiments.cycle_detection.min_repeats` configuration option.
+    #[arg(long)]
+    min_repeats: Option<usize>,
+
+    /// Instead of printing one series per soup, print the mean, median, quantiles and
+    /// bootstrap confidence interval across all soups at each poll.
+    #[arg(long)]
+    summary: bool,
+
+    /// Run the parameter sweep described in this file and exit. Each run prints its parameters
+    /// and the polled `observable` as a line of JSON.
+    #[arg(long)]
+    sweep: Option<String>,
 
     /// Make a default config file in the current directory and exit
     #[arg(short, long)]
@@ -80,6 +205,29 @@ struct Cli {
     /// Log each reaction
     #[arg(long)]
     log: bool,
+
+    /// When printing the final soup, print the name of each expression that is a known
+    /// combinator or Church encoded value next to it
+    #[arg(long)]
+    names: bool,
+}
+
+/// Find the experiment called `name`, or fail with the names of all experiments.
+fn find_experiment(name: &str) -> std::io::Result<&'static dyn experiments::Experiment> {
+    experiments::find(name).ok_or_else(|| {
+        let names = experiments::registry()
+            .iter()
+            .map(|e| e.name())
+            .collect::<Vec<_>>();
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "unknown experiment {}, expected one of: {}",
+                name,
+                names.join(", ")
+            ),
+        )
+    })
 }
 
 fn get_config(cli: &Cli) -> std::io::Result<config::Config> {
@@ -102,10 +250,85 @@ fn get_config(cli: &Cli) -> std::io::Result<config::Config> {
     if cli.log {
         config.set_verbose_logging(cli.log)
     }
+    if let Some(size) = cli.sample_size {
+        config.sample_size = size;
+    }
+    if cli.observable.needs_novelty() {
+        config.reactor_config.track_novelty = true;
+    }
+    if let Some(repeats) = cli.min_repeats {
+        config.experiments.cycle_detection.min_repeats = repeats;
+    }
+
+    // Flags apply to the selected experiment as well as to the global options
+    let experiment = cli.experiment.as_ref().or(cli.describe_experiment.as_ref());
+    if let Some(name) = experiment {
+        let section = find_experiment(name)?.section_mut(&mut config.experiments);
+        if let Some(limit) = cli.run_limit {
+            section.run_limit = limit;
+        }
+        if let Some(cutoff) = cli.reduction_cutoff {
+            section.reactor_config.reduction_cutoff = cutoff;
+        }
+        if let Some(interval) = cli.polling_interval {
+            section.polling_interval = interval;
+        }
+        if let Some(replicates) = cli.replicates {
+            section.replicates = replicates;
+        }
+        if let Some(size) = cli.sample_size {
+            section.sample_size = size;
+        }
+        if cli.observable.needs_novelty() {
+            section.reactor_config.track_novelty = true;
+        }
+    }
 
+    config
+        .validate()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
     Ok(config)
 }
 
+/// Set up the ensemble that runs `experiment`, resuming from the results directory if one is
+/// given. `cfg` is the configuration recorded in the manifest of the results directory, and
+/// `inputs` the hash of the expressions read from `stdin`, if the experiment reads any.
+fn get_ensemble(
+    cli: &Cli,
+    cfg: serde_json::Value,
+    experiment: &str,
+    inputs: Option<u64>,
+) -> std::io::Result<ensemble::Ensemble> {
+    let mut ensemble = ensemble::Ensemble::new(cli.workers);
+
+    let Some(path) = &cli.results_dir else {
+        if let Some(run_id) = &cli.run_id {
+            ensemble = ensemble.with_run_id(run_id);
+        }
+        return Ok(ensemble);
+    };
+
+    let run_id = match &cli.run_id {
+        Some(run_id) => run_id.clone(),
+        None => match ensemble::ResultsDir::read_manifest(path) {
+            Ok(manifest) => manifest.run_id,
+            Err(_) => Path::new(path)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| String::from("run")),
+        },
+    };
+    let manifest = ensemble::Manifest {
+        run_id,
+        experiment: experiment.to_string(),
+        config: cfg,
+        observable: cli.observable.to_string(),
+        inputs,
+    };
+    let dir = ensemble::ResultsDir::open(path, &manifest)?;
+    Ok(ensemble.with_run_id(&manifest.run_id).with_results_dir(dir))
+}
+
 /// Read lambda expressions from stdin and return an iterator over them
 pub fn read_inputs() -> impl Iterator<Item = Term> {
     let mut expression_strings = Vec::<String>::new();
@@ -127,18 +350,7 @@ pub fn read_inputs() -> impl Iterator<Item = Term> {
 }
 
 pub fn generate_expressions_and_seed_soup(cfg: &config::Config) -> soup::Soup {
-    let expressions = match &cfg.generator_config {
-        config::Generator::BTree(gen_cfg) => {
-            let mut gen = generators::BTreeGen::from_config(gen_cfg);
-            gen.generate_n(cfg.sample_size)
-        }
-        config::Generator::Fontana(gen_cfg) => {
-            let gen = generators::FontanaGen::from_config(gen_cfg);
-            std::iter::from_fn(move || gen.generate())
-                .take(cfg.sample_size)
-                .collect::<Vec<Term>>()
-        }
-    };
+    let expressions = generators::generate_from_config(&cfg.generator_config, cfg.sample_size);
     let mut soup = soup::Soup::from_config(&cfg.reactor_config);
     soup.perturb(expressions);
     soup
@@ -169,27 +381,73 @@ fn main() -> std::io::Result<()> {
         return Ok(());
     }
 
-    if let Some(e) = cli.experiment {
-        match e {
-            Experiment::XorsetStability => {}
-            Experiment::AdditionSearch => {
-                block_on(experiments::look_for_add());
-            }
-            Experiment::SyncEntropyTest => experiments::sync_entropy_test(),
-            Experiment::SampleScan => experiments::one_sample_with_dist(),
-            Experiment::XorsetSearch => {
-                block_on(experiments::look_for_xorset());
-            }
-            Experiment::EntropyTest => {
-                block_on(experiments::entropy_test());
-            }
-            Experiment::EntropySeries => {
-                block_on(experiments::entropy_series());
-            }
-            Experiment::SampleSimulate => {
-                block_on(experiments::simulate_sample());
-            }
+    if let Some(filename) = &cli.sweep {
+        let contents = read_to_string(filename)?;
+        let sweep = sweep::Sweep::from_sweep_str(&contents)?;
+        // Record the effective base configuration, so that a resumed sweep without its own
+        // `base` cannot silently run on a different one
+        let mut recorded = serde_json::to_value(&sweep)?;
+        if sweep.base.is_none() {
+            recorded["base"] = serde_json::to_value(&config)?;
+        }
+        let ensemble = get_ensemble(&cli, recorded, "sweep", None)?;
+        let recorder = cli
+            .abundances
+            .as_ref()
+            .map(|dir| abundance::AbundanceRecorder::new(dir, config.abundance_config.clone()));
+        let results = sweep
+            .run(&config, &ensemble, cli.observable, recorder)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let summary = cli.summary.then_some(cli.format);
+        sweep::write_results(&mut io::stdout(), results, summary)?;
+        return Ok(());
+    }
+
+    if cli.list_experiments {
+        for e in experiments::registry() {
+            println!("{}\t{}", e.name(), e.description());
+        }
+        return Ok(());
+    }
+
+    if let Some(name) = &cli.describe_experiment {
+        let e = find_experiment(name)?;
+        println!("{}: {}", e.name(), e.description());
+        for p in e.parameters(&config) {
+            println!("  {} = {}\n      {}", p.name, p.value, p.description);
+        }
+        return Ok(());
+    }
+
+    if let Some(name) = &cli.experiment {
+        let e = find_experiment(name)?;
+        if cli.abundances.is_some() && !e.records_abundances() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("experiment {} does not record abundances", e.name()),
+            ));
         }
+        let inputs = if e.reads_stdin() {
+            read_inputs().collect::<Vec<_>>()
+        } else {
+            Vec::new()
+        };
+        let hash = e.reads_stdin().then(|| ensemble::inputs_hash(&inputs));
+        let ensemble = get_ensemble(&cli, serde_json::to_value(&config)?, e.name(), hash)?;
+        let recorder = cli
+            .abundances
+            .as_ref()
+            .map(|dir| abundance::AbundanceRecorder::new(dir, config.abundance_config.clone()));
+        let ctx = experiments::Context {
+            ensemble: &ensemble,
+            config: &config,
+            observable: cli.observable,
+            summary: cli.summary,
+            inputs: &inputs,
+            abundances: recorder.as_ref(),
+        };
+        let report = e.run(&ctx)?;
+        experiments::write_report(report.as_ref(), cli.format, &mut io::stdout())?;
         return Ok(());
     }
 
@@ -202,15 +460,43 @@ fn main() -> std::io::Result<()> {
         generate_expressions_and_seed_soup(&config)
     };
 
+    let reactor = &config.reactor_config;
+    let classifier = || classifier::Classifier::new(reactor.reduction_cutoff, reactor.size_cutoff);
     if let Some(polling_interval) = config.polling_interval {
         let tape =
             soup.simulate_and_record(config.run_limit, polling_interval, config.verbose_logging);
         for soup in tape.history() {
-            println!("{}", soup.population_entropy());
+            println!("{}", cli.observable.measure(soup, &config.probe_config));
+        }
+        if let Some(dir) = &cli.abundances {
+            tape.abundances()
+                .finish(&config.abundance_config, &classifier())
+                .write_to_dir(dir)?;
+        }
+        if let Some(grain) = cli.detect_cycle {
+            let min_repeats = config.experiments.cycle_detection.min_repeats;
+            match tape.detect_cycle(grain, min_repeats, cli.observable, &config.probe_config) {
+                Some(cycle) => eprintln!(
+                    "cycle of period {} from poll {}, {} repeats, {} amplitude {}",
+                    cycle.period, cycle.start, cycle.repeats, cli.observable, cycle.amplitude
+                ),
+                None => eprintln!("no cycle"),
+            }
         }
     } else {
         soup.simulate_for(config.run_limit, config.verbose_logging);
-        soup.print();
+        if let Some(dir) = &cli.abundances {
+            let mut abundances = abundance::AbundanceTracker::new();
+            abundances.record_soup(&soup);
+            abundances
+                .finish(&config.abundance_config, &classifier())
+                .write_to_dir(dir)?;
+        }
+        if cli.names {
+            soup.print_named(&classifier());
+        } else {
+            soup.print();
+        }
     }
 
     Ok(())
@@ -0,0 +1,211 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
+use lambda_calculus::{abs, app, Term, Var};
+
+use crate::soup::{Soup, Tape};
+
+/// A subterm whose children are given by their identifiers in an `Interner`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Node {
+    Var(usize),
+    Abs(usize),
+    App(usize, usize),
+}
+
+/// Hash-consed table of subterms. Structurally equal subterms share one identifier, and each
+/// subterm is hashed from the identifiers of its children, so interning a term takes time
+/// linear in its size. Subterms are compared as written in De Bruijn notation, so a subterm
+/// with free variables only matches subterms that refer to the same enclosing binders.
+#[derive(Debug, Clone, Default)]
+pub struct Interner {
+    ids: HashMap<Node, usize>,
+
+    /// Each interned node, with its number of nodes
+    nodes: Vec<(Node, usize)>,
+}
+
+/// A subterm that recurs across the species of a soup.
+#[derive(Debug, Clone)]
+pub struct Motif {
+    pub expr: Term,
+
+    /// Number of nodes of the subterm
+    pub size: usize,
+
+    /// Number of times the subterm occurs, counting every copy of every species
+    pub occurrences: u64,
+
+    /// Number of distinct species that contain the subterm
+    pub species: usize,
+}
+
+/// Occurrences of every subterm of a soup, keyed by their identifiers in an `Interner`.
+#[derive(Debug, Clone, Default)]
+pub struct MotifCounts {
+    occurrences: HashMap<usize, u64>,
+    species: HashMap<usize, usize>,
+}
+
+/// Occurrences of a fixed set of motifs at every snapshot of a tape, followed by its final
+/// state.
+#[derive(Debug, Clone)]
+pub struct MotifSeries {
+    /// The motifs, as measured on the final state of the tape
+    pub motifs: Vec<Motif>,
+
+    /// `occurrences[poll][i]` is the number of occurrences of `motifs[i]` at that poll
+    pub occurrences: Vec<Vec<u64>>,
+
+    /// `species[poll][i]` is the number of species containing `motifs[i]` at that poll
+    pub species: Vec<Vec<usize>>,
+}
+
+impl Interner {
+    /// Create an empty table.
+    pub fn new() -> Self {
+        Interner::default()
+    }
+
+    /// Intern `expr` and all of its subterms, calling `visit` on the identifier of every
+    /// subterm, once per occurrence. Returns the identifier of `expr`.
+    pub(crate) fn intern_with(&mut self, expr: &Term, visit: &mut impl FnMut(usize)) -> usize {
+        let (node, size) = match expr {
+            Term::Var(i) => (Node::Var(*i), 1),
+            Term::Abs(body) => {
+                let body = self.intern_with(body, visit);
+                (Node::Abs(body), 1 + self.size(body))
+            }
+            Term::App(app) => {
+                let left = self.intern_with(&app.0, visit);
+                let right = self.intern_with(&app.1, visit);
+                (
+                    Node::App(left, right),
+                    1 + self.size(left) + self.size(right),
+                )
+            }
+        };
+        let id = match self.ids.get(&node) {
+            Some(id) => *id,
+            None => {
+                self.nodes.push((node, size));
+                self.ids.insert(node, self.nodes.len() - 1);
+                self.nodes.len() - 1
+            }
+        };
+        visit(id);
+        id
+    }
+
+    /// Number of nodes of the subterm with identifier `id`.
+    pub fn size(&self, id: usize) -> usize {
+        self.nodes[id].1
+    }
+
+    /// Whether the subterm with identifier `id` is a variable.
+    pub fn is_var(&self, id: usize) -> bool {
+        matches!(self.nodes[id].0, Node::Var(_))
+    }
+
+    /// The subterm with identifier `id`.
+    pub fn term(&self, id: usize) -> Term {
+        match self.nodes[id].0 {
+            Node::Var(i) => Var(i),
+            Node::Abs(body) => abs(self.term(body)),
+            Node::App(left, right) => app(self.term(left), self.term(right)),
+        }
+    }
+}
+
+impl MotifCounts {
+    /// Count the subterms of every expression of `soup`, interning them in `interner`. Sharing
+    /// an interner between soups gives their subterms the same identifiers.
+    pub fn new(soup: &Soup, interner: &mut Interner) -> Self {
+        let mut counts = MotifCounts::default();
+        for (expr, copies) in soup.expression_counts() {
+            let mut seen = HashSet::new();
+            interner.intern_with(&expr, &mut |id| {
+                *counts.occurrences.entry(id).or_default() += copies as u64;
+                seen.insert(id);
+            });
+            for id in seen {
+                *counts.species.entry(id).or_default() += 1;
+            }
+        }
+        counts
+    }
+
+    /// Number of occurrences of the subterm with identifier `id`, counting every copy of every
+    /// species.
+    pub fn occurrences(&self, id: usize) -> u64 {
+        self.occurrences.get(&id).copied().unwrap_or(0)
+    }
+
+    /// Number of distinct species containing the subterm with identifier `id`.
+    pub fn species(&self, id: usize) -> usize {
+        self.species.get(&id).copied().unwrap_or(0)
+    }
+
+    /// Identifiers of the `k` subterms with the most occurrences, leaving out variables and
+    /// subterms with fewer than `min_size` nodes. Ties are broken by the number of species that
+    /// contain the subterm, then by size, then by the printed subterm. Identifiers depend on the
+    /// order in which subterms were interned, so they are not used to break ties.
+    pub fn most_frequent(&self, interner: &Interner, k: usize, min_size: usize) -> Vec<usize> {
+        let mut ids = self
+            .occurrences
+            .keys()
+            .copied()
+            .filter(|id| !interner.is_var(*id) && interner.size(*id) >= min_size)
+            .collect::<Vec<_>>();
+        let key = |id: &usize| {
+            (
+                Reverse(self.occurrences(*id)),
+                Reverse(self.species(*id)),
+                Reverse(interner.size(*id)),
+            )
+        };
+        ids.sort_by(|a, b| {
+            key(a).cmp(&key(b)).then_with(|| {
+                let (a, b) = (interner.term(*a), interner.term(*b));
+                a.to_string().cmp(&b.to_string())
+            })
+        });
+        ids.truncate(k);
+        ids
+    }
+}
+
+impl Tape {
+    /// Follow the `k` most frequent subterms of the final state with at least `min_size` nodes
+    /// through every recorded snapshot of the soup, and the final state itself.
+    pub fn motif_series(&self, k: usize, min_size: usize) -> MotifSeries {
+        let mut interner = Interner::new();
+        let polls = self
+            .history()
+            .chain([self.final_state()])
+            .map(|soup| MotifCounts::new(soup, &mut interner))
+            .collect::<Vec<_>>();
+        let last = polls.last().unwrap();
+        let ids = last.most_frequent(&interner, k, min_size);
+
+        MotifSeries {
+            motifs: ids
+                .iter()
+                .map(|id| Motif {
+                    expr: interner.term(*id),
+                    size: interner.size(*id),
+                    occurrences: last.occurrences(*id),
+                    species: last.species(*id),
+                })
+                .collect(),
+            occurrences: polls
+                .iter()
+                .map(|c| ids.iter().map(|id| c.occurrences(*id)).collect())
+                .collect(),
+            species: polls
+                .iter()
+                .map(|c| ids.iter().map(|id| c.species(*id)).collect())
+                .collect(),
+        }
+    }
+}
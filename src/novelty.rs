@@ -0,0 +1,90 @@
+use std::collections::HashSet;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use lambda_calculus::Term;
+
+use crate::soup::Soup;
+
+/// Record of every species that has appeared in a soup. Only a hash of each species is kept,
+/// so the record stays small over long runs. Distinct species with the same hash are counted
+/// once, which is rare enough to ignore.
+#[derive(Debug, Clone, Default)]
+pub struct Novelty {
+    seen: HashSet<u64>,
+
+    /// Products never seen before, since the end of the last interval
+    interval: usize,
+
+    /// Products never seen before, since the start of the run
+    total: usize,
+}
+
+/// Hash of a species, stable within a run.
+pub fn species_hash(expr: &Term) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    expr.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl Novelty {
+    pub fn new() -> Self {
+        Novelty::default()
+    }
+
+    /// Add an expression that was introduced into the soup from outside, without counting it as
+    /// novel.
+    pub fn seed(&mut self, expr: &Term) {
+        self.seen.insert(species_hash(expr));
+    }
+
+    /// Add a product of a reaction, and return whether it was never seen before.
+    pub fn record(&mut self, expr: &Term) -> bool {
+        let novel = self.seen.insert(species_hash(expr));
+        if novel {
+            self.interval += 1;
+            self.total += 1;
+        }
+        novel
+    }
+
+    /// Start a new interval, resetting the count of novel products.
+    pub fn end_interval(&mut self) {
+        self.interval = 0;
+    }
+
+    /// Number of novel products since the end of the last interval.
+    pub fn novel_in_interval(&self) -> usize {
+        self.interval
+    }
+
+    /// Number of novel products since the start of the run.
+    pub fn novel_total(&self) -> usize {
+        self.total
+    }
+
+    /// Number of distinct species that have appeared in the soup, including seeds.
+    pub fn cumulative_distinct(&self) -> usize {
+        self.seen.len()
+    }
+}
+
+impl Soup {
+    /// Number of reaction products never seen before in the run, since the last poll of
+    /// `simulate_and_poll` or one of its variants. `None` if the soup does not track novelty,
+    /// see `config::Reactor::track_novelty`.
+    pub fn novel_in_interval(&self) -> Option<usize> {
+        self.novelty().map(Novelty::novel_in_interval)
+    }
+
+    /// Number of reaction products never seen before, since the start of the run. `None` if the
+    /// soup does not track novelty.
+    pub fn novel_total(&self) -> Option<usize> {
+        self.novelty().map(Novelty::novel_total)
+    }
+
+    /// Number of distinct species that have appeared in the soup since the start of the run.
+    /// `None` if the soup does not track novelty.
+    pub fn cumulative_distinct(&self) -> Option<usize> {
+        self.novelty().map(Novelty::cumulative_distinct)
+    }
+}
@@ -0,0 +1,118 @@
+use lambda_calculus::Term;
+
+use crate::interaction::{InteractionMatrix, Product};
+use crate::soup::Soup;
+
+/// Replicative behavior of a species, measured against the other frequent species of a soup.
+#[derive(Debug, Clone)]
+pub struct Replicator {
+    pub expr: Term,
+
+    /// Number of copies of the species in the soup
+    pub count: u32,
+
+    /// Whether colliding the species with itself reproduces it, under every rule
+    pub self_copy: bool,
+
+    /// Fraction of collisions with the species on the left that reproduce it, weighted by the
+    /// abundance of the other species
+    pub left_copy_rate: f32,
+
+    /// Fraction of collisions with the species on the right that reproduce it, weighted by the
+    /// abundance of the other species
+    pub right_copy_rate: f32,
+
+    /// Indices of the species this one regenerates, and is regenerated by, in the ranking
+    pub partners: Vec<usize>,
+}
+
+/// Whether some rule turns the collision of `i` and `j` into `target`.
+fn produces(matrix: &InteractionMatrix, i: usize, j: usize, target: usize) -> bool {
+    matrix.products(i, j).any(|p| p == Product::Species(target))
+}
+
+/// Fraction of products of `i` with every other species that reproduce `i`, weighted by the
+/// abundance of the other species. If `left` is set, `i` is the left reactant.
+fn copy_rate(matrix: &InteractionMatrix, i: usize, left: bool) -> f32 {
+    let mut copies = 0.0;
+    let mut total = 0.0;
+    for (j, (_, count)) in matrix.species().iter().enumerate() {
+        if i == j {
+            continue;
+        }
+        let (l, r) = if left { (i, j) } else { (j, i) };
+        for p in matrix.products(l, r) {
+            if p == Product::Species(i) {
+                copies += *count as f32;
+            }
+            total += *count as f32;
+        }
+    }
+    if total == 0.0 {
+        return 0.0;
+    }
+    copies / total
+}
+
+/// Whether `i` and `j` regenerate each other: `j` acting on one of the pair produces `i`, and
+/// `i` acting on one of the pair produces `j`.
+fn regenerate(matrix: &InteractionMatrix, i: usize, j: usize) -> bool {
+    let builds =
+        |f: usize, target: usize| produces(matrix, f, i, target) || produces(matrix, f, j, target);
+    i != j && builds(j, i) && builds(i, j)
+}
+
+impl Replicator {
+    /// Mean of the left and right copy rates, used to rank replicators.
+    pub fn score(&self) -> f32 {
+        (self.left_copy_rate + self.right_copy_rate) / 2.0
+    }
+}
+
+impl Soup {
+    /// Rank the `k` most frequent species by how often they reproduce themselves when they
+    /// collide with each other, using the same reduction limits as a reaction. Copies of parents
+    /// are counted even if the reactor discards them. `Replicator::partners` refers to indices
+    /// in the returned ranking.
+    pub fn replicators(&self, k: usize) -> Vec<Replicator> {
+        let matrix = InteractionMatrix::from_soup(self, k);
+        let n = matrix.len();
+        let replicators = matrix
+            .species()
+            .iter()
+            .enumerate()
+            .map(|(i, (expr, count))| Replicator {
+                expr: expr.clone(),
+                count: *count,
+                self_copy: matrix.products(i, i).all(|p| p == Product::Species(i)),
+                left_copy_rate: copy_rate(&matrix, i, true),
+                right_copy_rate: copy_rate(&matrix, i, false),
+                partners: (0..n).filter(|j| regenerate(&matrix, i, *j)).collect(),
+            })
+            .collect::<Vec<_>>();
+
+        let mut order = (0..n).collect::<Vec<_>>();
+        order.sort_by(|a, b| {
+            let (a, b) = (&replicators[*a], &replicators[*b]);
+            b.score()
+                .total_cmp(&a.score())
+                .then(b.self_copy.cmp(&a.self_copy))
+                .then(b.count.cmp(&a.count))
+        });
+        let mut rank = vec![0; n];
+        for (r, i) in order.iter().enumerate() {
+            rank[*i] = r;
+        }
+        order
+            .iter()
+            .map(|i| {
+                let mut replicator = replicators[*i].clone();
+                for p in replicator.partners.iter_mut() {
+                    *p = rank[*p];
+                }
+                replicator.partners.sort();
+                replicator
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,277 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use crate::diversity::Base;
+use crate::soup::Soup;
+
+/// Number of copies of each species, keyed by species.
+pub type Counts<K> = HashMap<K, u32>;
+
+fn count<K: Hash + Eq>(counts: &Counts<K>, k: &K) -> f64 {
+    counts.get(k).copied().unwrap_or(0) as f64
+}
+
+fn total<K>(counts: &Counts<K>) -> f64 {
+    counts.values().map(|c| *c as f64).sum()
+}
+
+/// All species present in either `a` or `b`.
+fn union<'a, K: Hash + Eq>(a: &'a Counts<K>, b: &'a Counts<K>) -> HashSet<&'a K> {
+    a.keys().chain(b.keys()).collect()
+}
+
+/// Jaccard index of the sets of species present in `a` and `b`, ignoring abundance. Two empty
+/// soups are identical.
+pub fn jaccard<K: Hash + Eq>(a: &Counts<K>, b: &Counts<K>) -> f64 {
+    let a = a.iter().filter(|(_, c)| **c > 0).map(|(k, _)| k);
+    let b = b.iter().filter(|(_, c)| **c > 0).map(|(k, _)| k);
+    let a = a.collect::<HashSet<_>>();
+    let b = b.collect::<HashSet<_>>();
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(&b).count() as f64 / union as f64
+}
+
+/// Weighted Jaccard index, the sum of the smaller counts of each species divided by the sum of
+/// the larger counts. Two empty soups are identical.
+pub fn weighted_jaccard<K: Hash + Eq>(a: &Counts<K>, b: &Counts<K>) -> f64 {
+    let (mut min, mut max) = (0.0, 0.0);
+    for k in union(a, b) {
+        let (x, y) = (count(a, k), count(b, k));
+        min += x.min(y);
+        max += x.max(y);
+    }
+    if max == 0.0 {
+        return 1.0;
+    }
+    min / max
+}
+
+/// Bray-Curtis dissimilarity, from 0 for identical counts to 1 for soups that share no species.
+pub fn bray_curtis<K: Hash + Eq>(a: &Counts<K>, b: &Counts<K>) -> f64 {
+    let sum = total(a) + total(b);
+    if sum == 0.0 {
+        return 0.0;
+    }
+    let shared = union(a, b)
+        .into_iter()
+        .map(|k| count(a, k).min(count(b, k)))
+        .sum::<f64>();
+    1.0 - 2.0 * shared / sum
+}
+
+/// Cosine similarity of the abundance vectors of `a` and `b`. Zero if either soup is empty.
+pub fn cosine<K: Hash + Eq>(a: &Counts<K>, b: &Counts<K>) -> f64 {
+    let dot = a.iter().map(|(k, x)| *x as f64 * count(b, k)).sum::<f64>();
+    let norm = |c: &Counts<K>| c.values().map(|x| (*x as f64).powi(2)).sum::<f64>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms == 0.0 {
+        return 0.0;
+    }
+    dot / norms
+}
+
+/// Kullback-Leibler divergence of the species distribution of `b` from that of `a`. Infinite if
+/// `a` has a species that `b` does not.
+pub fn kl_divergence<K: Hash + Eq>(a: &Counts<K>, b: &Counts<K>, base: Base) -> f64 {
+    let (ta, tb) = (total(a), total(b));
+    let mut divergence = 0.0;
+    for (k, x) in a {
+        if *x == 0 {
+            continue;
+        }
+        let p = *x as f64 / ta;
+        let q = count(b, k) / tb;
+        if q == 0.0 {
+            return f64::INFINITY;
+        }
+        divergence += p * base.log(p / q);
+    }
+    divergence
+}
+
+/// Jensen-Shannon divergence between the species distributions of `a` and `b`. Symmetric, and
+/// always finite: at most 1 bit. Two empty soups are identical, and an empty soup is as far as
+/// possible from any other soup.
+pub fn js_divergence<K: Hash + Eq>(a: &Counts<K>, b: &Counts<K>, base: Base) -> f64 {
+    let (ta, tb) = (total(a), total(b));
+    if ta == 0.0 || tb == 0.0 {
+        return if ta == tb { 0.0 } else { base.log(2.0) };
+    }
+    let mut divergence = 0.0;
+    for k in union(a, b) {
+        let p = count(a, k) / ta;
+        let q = count(b, k) / tb;
+        let m = (p + q) / 2.0;
+        if p > 0.0 {
+            divergence += p * base.log(p / m) / 2.0;
+        }
+        if q > 0.0 {
+            divergence += q * base.log(q / m) / 2.0;
+        }
+    }
+    divergence
+}
+
+/// The `k` species with the most copies. Ties are broken by the hash of the species, so that
+/// equal counts always give the same species.
+pub fn most_abundant<K: Hash + Eq>(counts: &Counts<K>, k: usize) -> HashSet<&K> {
+    let hash = |k: &K| {
+        let mut hasher = DefaultHasher::new();
+        k.hash(&mut hasher);
+        hasher.finish()
+    };
+    let mut species = counts.iter().filter(|(_, c)| **c > 0).collect::<Vec<_>>();
+    species.sort_by_key(|(k, c)| (Reverse(**c), hash(k)));
+    species.into_iter().take(k).map(|(k, _)| k).collect()
+}
+
+/// Overlap of the `k` most abundant species of `a` and `b`: the number of species in both sets,
+/// divided by the size of the larger set. Two empty soups overlap fully.
+pub fn top_k_overlap<K: Hash + Eq>(a: &Counts<K>, b: &Counts<K>, k: usize) -> f64 {
+    let (a, b) = (most_abundant(a, k), most_abundant(b, k));
+    let size = a.len().max(b.len());
+    if size == 0 {
+        return 1.0;
+    }
+    a.intersection(&b).count() as f64 / size as f64
+}
+
+impl Soup {
+    /// Jaccard index of the sets of species of two soups, see `similarity::jaccard`.
+    pub fn jaccard(&self, other: &Soup) -> f64 {
+        jaccard(&self.expression_counts(), &other.expression_counts())
+    }
+
+    /// Weighted Jaccard index of two soups, see `similarity::weighted_jaccard`.
+    pub fn weighted_jaccard(&self, other: &Soup) -> f64 {
+        weighted_jaccard(&self.expression_counts(), &other.expression_counts())
+    }
+
+    /// Bray-Curtis dissimilarity of two soups, see `similarity::bray_curtis`.
+    pub fn bray_curtis(&self, other: &Soup) -> f64 {
+        bray_curtis(&self.expression_counts(), &other.expression_counts())
+    }
+
+    /// Cosine similarity of two soups, see `similarity::cosine`.
+    pub fn cosine_similarity(&self, other: &Soup) -> f64 {
+        cosine(&self.expression_counts(), &other.expression_counts())
+    }
+
+    /// Kullback-Leibler divergence of `other` from this soup, see `similarity::kl_divergence`.
+    pub fn kl_divergence(&self, other: &Soup, base: Base) -> f64 {
+        kl_divergence(&self.expression_counts(), &other.expression_counts(), base)
+    }
+
+    /// Jensen-Shannon divergence of two soups, see `similarity::js_divergence`.
+    pub fn js_divergence(&self, other: &Soup, base: Base) -> f64 {
+        js_divergence(&self.expression_counts(), &other.expression_counts(), base)
+    }
+
+    /// Overlap of the most frequent species of two soups, see `similarity::top_k_overlap`.
+    pub fn top_k_overlap(&self, other: &Soup, k: usize) -> f64 {
+        top_k_overlap(&self.expression_counts(), &other.expression_counts(), k)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(pairs: &[(&'static str, u32)]) -> Counts<&'static str> {
+        pairs.iter().copied().collect()
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-12
+    }
+
+    #[test]
+    fn identical_soups() {
+        let a = counts(&[("x", 3), ("y", 1)]);
+        assert_eq!(jaccard(&a, &a), 1.0);
+        assert_eq!(weighted_jaccard(&a, &a), 1.0);
+        assert_eq!(bray_curtis(&a, &a), 0.0);
+        assert!(close(cosine(&a, &a), 1.0));
+        assert_eq!(kl_divergence(&a, &a, Base::Bits), 0.0);
+        assert_eq!(js_divergence(&a, &a, Base::Bits), 0.0);
+    }
+
+    #[test]
+    fn disjoint_soups() {
+        let a = counts(&[("x", 2)]);
+        let b = counts(&[("y", 5)]);
+        assert_eq!(jaccard(&a, &b), 0.0);
+        assert_eq!(weighted_jaccard(&a, &b), 0.0);
+        assert_eq!(bray_curtis(&a, &b), 1.0);
+        assert_eq!(cosine(&a, &b), 0.0);
+        assert_eq!(kl_divergence(&a, &b, Base::Bits), f64::INFINITY);
+        assert!(close(js_divergence(&a, &b, Base::Bits), 1.0));
+    }
+
+    #[test]
+    fn partial_overlap() {
+        let a = counts(&[("x", 2), ("y", 2)]);
+        let b = counts(&[("y", 1), ("z", 3)]);
+        // Species {x, y} and {y, z} share one of three
+        assert!(close(jaccard(&a, &b), 1.0 / 3.0));
+        // min: x 0, y 1, z 0; max: x 2, y 2, z 3
+        assert!(close(weighted_jaccard(&a, &b), 1.0 / 7.0));
+        // 1 - 2 * 1 / (4 + 4)
+        assert!(close(bray_curtis(&a, &b), 0.75));
+        // (2 * 1) / (sqrt(8) * sqrt(10))
+        assert!(close(cosine(&a, &b), 2.0 / 80f64.sqrt()));
+    }
+
+    #[test]
+    fn scaled_counts() {
+        let a = counts(&[("x", 1), ("y", 3)]);
+        let b = counts(&[("x", 2), ("y", 6)]);
+        assert_eq!(jaccard(&a, &b), 1.0);
+        assert!(close(weighted_jaccard(&a, &b), 0.5));
+        assert!(close(cosine(&a, &b), 1.0));
+        assert!(close(kl_divergence(&a, &b, Base::Nats), 0.0));
+        assert!(close(js_divergence(&a, &b, Base::Nats), 0.0));
+    }
+
+    #[test]
+    fn divergence_of_coin_flips() {
+        let fair = counts(&[("h", 1), ("t", 1)]);
+        let biased = counts(&[("h", 3), ("t", 1)]);
+        // 0.5 log2(0.5 / 0.75) + 0.5 log2(0.5 / 0.25)
+        let expected = 0.5 * (2.0f64 / 3.0).log2() + 0.5;
+        assert!(close(kl_divergence(&fair, &biased, Base::Bits), expected));
+        assert!(close(
+            js_divergence(&fair, &biased, Base::Bits),
+            js_divergence(&biased, &fair, Base::Bits)
+        ));
+    }
+
+    #[test]
+    fn top_k_overlap_of_ranked_species() {
+        let a = counts(&[("x", 5), ("y", 4), ("z", 1)]);
+        let b = counts(&[("x", 1), ("y", 6), ("w", 3)]);
+        assert_eq!(top_k_overlap(&a, &a, 2), 1.0);
+        // {x, y} and {y, w}
+        assert!(close(top_k_overlap(&a, &b, 2), 0.5));
+        assert!(close(top_k_overlap(&a, &b, 3), 2.0 / 3.0));
+        assert_eq!(top_k_overlap(&a, &counts(&[]), 2), 0.0);
+    }
+
+    #[test]
+    fn empty_soups() {
+        let a = counts(&[]);
+        assert_eq!(jaccard(&a, &a), 1.0);
+        assert_eq!(weighted_jaccard(&a, &a), 1.0);
+        assert_eq!(bray_curtis(&a, &a), 0.0);
+        assert_eq!(cosine(&a, &a), 0.0);
+        assert_eq!(top_k_overlap(&a, &a, 3), 1.0);
+        assert_eq!(js_divergence(&a, &a, Base::Bits), 0.0);
+        let b = counts(&[("x", 2)]);
+        assert_eq!(js_divergence(&a, &b, Base::Bits), 1.0);
+        assert!(close(js_divergence(&b, &a, Base::Nats), 2f64.ln()));
+    }
+}
@@ -2,6 +2,8 @@ use core::fmt;
 use std::fmt::{Debug, Display};
 
 use crate::config;
+use crate::novelty::Novelty;
+use crate::stopping::{Detector, Stop, StopReason};
 use lambda_calculus::{abs, app, Term, Var};
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha8Rng;
@@ -12,6 +14,8 @@ use rand_chacha::ChaCha8Rng;
 pub struct Soup {
     expressions: Vec<Term>,
     n_collisions: usize,
+    n_reactions: usize,
+    n_failures: usize,
     reaction_rules: Vec<Term>,
     reduction_limit: usize,
     size_limit: usize,
@@ -22,6 +26,9 @@ pub struct Soup {
     discard_free_variable_expressions: bool,
     discard_parents: bool,
 
+    /// Species seen so far, if the soup tracks novelty
+    novelty: Option<Novelty>,
+
     rng: ChaCha8Rng,
 }
 
@@ -106,8 +113,11 @@ impl Soup {
             discard_parents: cfg.discard_parents,
             discard_identity: cfg.discard_identity,
             discard_free_variable_expressions: cfg.discard_free_variable_expressions,
+            novelty: cfg.track_novelty.then(Novelty::new),
             rng,
             n_collisions: 0,
+            n_reactions: 0,
+            n_failures: 0,
         }
     }
 
@@ -116,23 +126,59 @@ impl Soup {
         self.reduction_limit = limit;
     }
 
+    /// Largest number of reduction steps of a collision.
+    pub fn reduction_limit(&self) -> usize {
+        self.reduction_limit
+    }
+
+    /// Largest size of an expression during a collision.
+    pub fn size_limit(&self) -> usize {
+        self.size_limit
+    }
+
+    /// Replace the random number generator of the soup by one seeded with `seed`.
+    pub fn reseed(&mut self, seed: [u8; 32]) {
+        self.rng = ChaCha8Rng::from_seed(seed);
+    }
+
+    /// Replace one expression of the soup, chosen with `rng`, by a copy of a different
+    /// expression of the soup. The random number generator of the soup is left untouched.
+    /// Returns `false`, leaving the soup unchanged, if all expressions are the same.
+    pub fn replace_one(&mut self, rng: &mut impl Rng) -> bool {
+        let n = self.expressions.len();
+        if n < 2 {
+            return false;
+        }
+        let i = rng.gen_range(0..n);
+        let others = (0..n)
+            .filter(|j| self.expressions[*j] != self.expressions[i])
+            .collect::<Vec<_>>();
+        if others.is_empty() {
+            return false;
+        }
+        let j = others[rng.gen_range(0..others.len())];
+        self.expressions[i] = self.expressions[j].clone();
+        true
+    }
+
     /// Introduce all expressions in `expressions` into the soup, without
     /// reduction.
     pub fn perturb(&mut self, expressions: impl IntoIterator<Item = Term>) {
+        let start = self.expressions.len();
         self.expressions
             .extend(expressions.into_iter().filter(|e| !e.has_free_variables()));
+        if let Some(novelty) = &mut self.novelty {
+            for expr in &self.expressions[start..] {
+                novelty.seed(expr);
+            }
+        }
     }
 
     /// Return the result of ((`rule` `left`) `right`), up to a limit of
     /// `self.reduction_limit`.
     // TODO: return a proper error type instead of `String`.
     fn collide(&self, rule: Term, left: Term, right: Term) -> Result<(Term, usize), ReactionError> {
-        let mut expr = app!(rule, left.clone(), right.clone());
-        let n = reduce_with_limit(&mut expr, self.reduction_limit, self.size_limit)?;
-
-        if n == self.reduction_limit {
-            return Err(ReactionError::ExceedsReductionLimit);
-        }
+        let (expr, n) = self.reduce_collision(rule, left.clone(), right.clone())?;
 
         let identity = abs(Var(1));
         if expr.is_isomorphic_to(&identity) && self.discard_identity {
@@ -152,6 +198,36 @@ impl Soup {
         Ok((expr, n))
     }
 
+    /// Reduce ((`rule` `left`) `right`) within the limits of the soup, without applying any of
+    /// the filters of `collide`.
+    fn reduce_collision(
+        &self,
+        rule: Term,
+        left: Term,
+        right: Term,
+    ) -> Result<(Term, usize), ReactionError> {
+        let mut expr = app!(rule, left, right);
+        let n = reduce_with_limit(&mut expr, self.reduction_limit, self.size_limit)?;
+
+        if n == self.reduction_limit {
+            return Err(ReactionError::ExceedsReductionLimit);
+        }
+        Ok((expr, n))
+    }
+
+    /// Return the product of `left` and `right` under each reaction rule, without changing the
+    /// soup. Products are reduced with the same limits as in a reaction, but are not filtered,
+    /// so that copies of the parents and identities are kept.
+    pub fn products(&self, left: &Term, right: &Term) -> Vec<Result<Term, ReactionError>> {
+        self.reaction_rules
+            .iter()
+            .map(|rule| {
+                self.reduce_collision(rule.clone(), left.clone(), right.clone())
+                    .map(|(expr, _)| expr)
+            })
+            .collect()
+    }
+
     /// Produce one atomic reaction on the soup.
     fn react(&mut self) -> Result<ReactionResult, ReactionError> {
         let n_expr = self.expressions.len();
@@ -200,6 +276,11 @@ impl Soup {
         }
 
         // Add collision results to soup
+        if let Some(novelty) = &mut self.novelty {
+            for value in &buf {
+                novelty.record(value);
+            }
+        }
         self.expressions.append(&mut buf);
 
         // Add removed parents back into the soup, if necessary
@@ -224,6 +305,16 @@ impl Soup {
         })
     }
 
+    /// Produce one reaction, and keep track of whether it failed.
+    fn step(&mut self) -> Result<ReactionResult, ReactionError> {
+        let reaction = self.react();
+        self.n_reactions += 1;
+        if reaction.is_err() {
+            self.n_failures += 1;
+        }
+        reaction
+    }
+
     fn log_failure_reaction(reaction: &Result<ReactionResult, ReactionError>) {
         if let Err(message) = reaction {
             println!("failed because {}", message)
@@ -250,7 +341,7 @@ impl Soup {
     pub fn simulate_for(&mut self, n: usize, log: bool) -> usize {
         let mut n_successes = 0;
         for _ in 0..n {
-            let reaction = self.react();
+            let reaction = self.step();
             if reaction.is_ok() {
                 n_successes += 1;
             }
@@ -269,16 +360,17 @@ impl Soup {
         n: usize,
         polling_interval: usize,
         log: bool,
-        poller: F,
+        mut poller: F,
     ) -> Vec<T>
     where
-        F: Fn(&Self) -> T,
+        F: FnMut(&Self) -> T,
     {
         let mut data: Vec<T> = Vec::new();
         for i in 0..n {
-            let reaction = self.react();
+            let reaction = self.step();
             if (i % polling_interval) == 0 {
-                data.push(poller(self))
+                data.push(poller(self));
+                self.end_novelty_interval();
             }
             if log {
                 let message = Soup::log_message_from_reaction(&reaction);
@@ -300,10 +392,11 @@ impl Soup {
     {
         let mut data: Vec<T> = Vec::new();
         for i in 0..n {
-            let reaction = self.react();
+            let reaction = self.step();
             if (i % polling_interval) == 0 {
                 let (datum, should_kill) = killpoller(self);
                 data.push(datum);
+                self.end_novelty_interval();
                 if should_kill {
                     return data;
                 };
@@ -316,15 +409,63 @@ impl Soup {
         data
     }
 
+    /// Simulate the soup for up to `n` reactions, calling `poller` every `polling_interval`
+    /// reactions, and stop early as soon as one of the `criteria` is met. Returns the polled
+    /// data, and why and when the run stopped. Turns on novelty tracking if the criteria need it.
+    pub fn simulate_and_poll_until<F, T>(
+        &mut self,
+        n: usize,
+        polling_interval: usize,
+        log: bool,
+        criteria: &config::Stopping,
+        mut poller: F,
+    ) -> (Vec<T>, Stop)
+    where
+        F: FnMut(&Self) -> T,
+    {
+        if criteria.novelty_collisions.is_some() {
+            self.track_novelty();
+        }
+        let mut detector = Detector::new(criteria, self);
+        let mut data: Vec<T> = Vec::new();
+        for i in 0..n {
+            let reaction = self.step();
+            if log {
+                let message = Soup::log_message_from_reaction(&reaction);
+                println!("reaction {:?} {}", i, message)
+            }
+            if (i % polling_interval) == 0 {
+                data.push(poller(self));
+                let reason = detector.check(self);
+                self.end_novelty_interval();
+                if let Some(reason) = reason {
+                    let stop = Stop {
+                        reason,
+                        reactions: i + 1,
+                        collisions: self.n_collisions,
+                    };
+                    return (data, stop);
+                }
+            }
+        }
+        let stop = Stop {
+            reason: StopReason::RunLimit,
+            reactions: n,
+            collisions: self.n_collisions,
+        };
+        (data, stop)
+    }
+
     /// Simulate the soup for `n` collisions, recording the state of the soup every
     /// `polling_interval` reactions. If `log` is set, then print out a log message for each
     /// reaction
     pub fn simulate_and_record(&mut self, n: usize, polling_interval: usize, log: bool) -> Tape {
         let mut history: Vec<Soup> = Vec::new();
         for i in 0..n {
-            let reaction = self.react();
+            let reaction = self.step();
             if (i % polling_interval) == 0 {
-                history.push(self.clone())
+                history.push(self.clone());
+                self.end_novelty_interval();
             }
             if log {
                 let message = Soup::log_message_from_reaction(&reaction);
@@ -361,6 +502,38 @@ impl Soup {
     pub fn collisions(&self) -> usize {
         self.n_collisions
     }
+
+    /// Species seen so far, if the soup tracks novelty.
+    pub fn novelty(&self) -> Option<&Novelty> {
+        self.novelty.as_ref()
+    }
+
+    /// Start tracking novelty, if the soup does not already. Expressions already in the soup
+    /// are seen, but not novel.
+    pub fn track_novelty(&mut self) {
+        if self.novelty.is_none() {
+            let mut novelty = Novelty::new();
+            for expr in &self.expressions {
+                novelty.seed(expr);
+            }
+            self.novelty = Some(novelty);
+        }
+    }
+
+    /// Start a new novelty interval. Called after every poll.
+    fn end_novelty_interval(&mut self) {
+        if let Some(novelty) = &mut self.novelty {
+            novelty.end_interval();
+        }
+    }
+
+    /// Get the fraction of reactions that have failed since the soup was created.
+    pub fn failure_rate(&self) -> f32 {
+        if self.n_reactions == 0 {
+            return 0.0;
+        }
+        self.n_failures as f32 / self.n_reactions as f32
+    }
 }
 
 impl Tape {
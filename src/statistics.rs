@@ -0,0 +1,208 @@
+use std::io::{self, Write};
+
+use clap::ValueEnum;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::Serialize;
+
+/// Output format for ensemble summaries.
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+/// Options controlling how a series is summarized across replicates.
+#[derive(Debug, Clone)]
+pub struct SummaryOptions {
+    /// Quantile levels to report, each in `[0, 1]`. Default: `[0.05, 0.25, 0.75, 0.95]`.
+    pub quantiles: Vec<f64>,
+
+    /// Number of bootstrap resamples used for the confidence interval of the mean. Default:
+    /// `1000`.
+    pub bootstrap_samples: usize,
+
+    /// Confidence level of the bootstrap interval. Default: `0.95`.
+    pub confidence: f64,
+
+    /// Seed for the bootstrap resampler. Default: all zeros.
+    pub seed: [u8; 32],
+}
+
+/// Summary of a scalar series across replicates, at a single poll.
+#[derive(Debug, Clone, Serialize)]
+pub struct PollSummary {
+    /// Index of the poll
+    pub poll: usize,
+
+    /// Number of replicates with a value at this poll. Replicates that stopped early do not
+    /// contribute to later polls.
+    pub n: usize,
+
+    pub mean: f64,
+    pub median: f64,
+
+    /// Values at each level of `SummaryOptions::quantiles`, in the same order.
+    pub quantiles: Vec<f64>,
+
+    /// Lower bound of the bootstrap confidence interval of the mean
+    pub ci_lower: f64,
+
+    /// Upper bound of the bootstrap confidence interval of the mean
+    pub ci_upper: f64,
+}
+
+/// Collects one scalar series per replicate, and summarizes them poll by poll.
+#[derive(Debug, Default)]
+pub struct SeriesAggregator {
+    series: Vec<Vec<f64>>,
+}
+
+impl SummaryOptions {
+    pub fn new() -> Self {
+        SummaryOptions {
+            quantiles: vec![0.05, 0.25, 0.75, 0.95],
+            bootstrap_samples: 1000,
+            confidence: 0.95,
+            seed: [0; 32],
+        }
+    }
+}
+
+impl Default for SummaryOptions {
+    fn default() -> Self {
+        SummaryOptions::new()
+    }
+}
+
+/// Linearly interpolated quantile `q` of `sorted`, which must be sorted in ascending order and
+/// not empty.
+pub fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let position = q.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let lower = position.floor() as usize;
+    let upper = position.ceil() as usize;
+    let fraction = position - lower as f64;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * fraction
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Percentile bootstrap confidence interval for the mean of `values`.
+fn bootstrap_mean_interval(
+    values: &[f64],
+    options: &SummaryOptions,
+    rng: &mut ChaCha8Rng,
+) -> (f64, f64) {
+    if options.bootstrap_samples == 0 {
+        let m = mean(values);
+        return (m, m);
+    }
+
+    let mut means = (0..options.bootstrap_samples)
+        .map(|_| {
+            let total: f64 = (0..values.len())
+                .map(|_| values[rng.gen_range(0..values.len())])
+                .sum();
+            total / values.len() as f64
+        })
+        .collect::<Vec<f64>>();
+    means.sort_by(f64::total_cmp);
+
+    let alpha = 1.0 - options.confidence;
+    (
+        quantile(&means, alpha / 2.0),
+        quantile(&means, 1.0 - alpha / 2.0),
+    )
+}
+
+impl SeriesAggregator {
+    pub fn new() -> Self {
+        SeriesAggregator { series: Vec::new() }
+    }
+
+    /// Add the series of one replicate.
+    pub fn push(&mut self, series: impl IntoIterator<Item = f32>) {
+        self.series
+            .push(series.into_iter().map(|x| x as f64).collect());
+    }
+
+    /// Summarize all collected series at each poll. The result does not depend on the order
+    /// in which replicates were pushed.
+    pub fn summarize(&self, options: &SummaryOptions) -> Vec<PollSummary> {
+        let mut rng = ChaCha8Rng::from_seed(options.seed);
+        let n_polls = self.series.iter().map(|s| s.len()).max().unwrap_or(0);
+
+        (0..n_polls)
+            .map(|poll| {
+                let mut values = self
+                    .series
+                    .iter()
+                    .filter_map(|s| s.get(poll).copied())
+                    .collect::<Vec<f64>>();
+                values.sort_by(f64::total_cmp);
+
+                let (ci_lower, ci_upper) = bootstrap_mean_interval(&values, options, &mut rng);
+                PollSummary {
+                    poll,
+                    n: values.len(),
+                    mean: mean(&values),
+                    median: quantile(&values, 0.5),
+                    quantiles: options
+                        .quantiles
+                        .iter()
+                        .map(|q| quantile(&values, *q))
+                        .collect(),
+                    ci_lower,
+                    ci_upper,
+                }
+            })
+            .collect()
+    }
+}
+
+/// CSV header for summaries produced with `options`.
+pub fn csv_header(options: &SummaryOptions) -> String {
+    let mut header = String::from("poll,n,mean,median");
+    for q in &options.quantiles {
+        header.push_str(&format!(",q{}", q));
+    }
+    header.push_str(",ci_lower,ci_upper");
+    header
+}
+
+impl PollSummary {
+    /// Format this summary as a CSV row matching `csv_header`.
+    pub fn to_csv_row(&self) -> String {
+        let mut row = format!("{},{},{},{}", self.poll, self.n, self.mean, self.median);
+        for q in &self.quantiles {
+            row.push_str(&format!(",{}", q));
+        }
+        row.push_str(&format!(",{},{}", self.ci_lower, self.ci_upper));
+        row
+    }
+}
+
+/// Write `summaries` to `out` in the given `format`. `options` must be the options the
+/// summaries were produced with, and is used to label quantile columns.
+pub fn write_summaries(
+    out: &mut impl Write,
+    summaries: &[PollSummary],
+    options: &SummaryOptions,
+    format: Format,
+) -> io::Result<()> {
+    match format {
+        Format::Json => {
+            serde_json::to_writer(&mut *out, summaries)?;
+            writeln!(out)
+        }
+        Format::Csv => {
+            writeln!(out, "{}", csv_header(options))?;
+            for s in summaries {
+                writeln!(out, "{}", s.to_csv_row())?;
+            }
+            Ok(())
+        }
+    }
+}
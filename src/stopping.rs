@@ -0,0 +1,124 @@
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+
+use lambda_calculus::Term;
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::similarity::most_abundant;
+use crate::soup::Soup;
+
+/// Why a run stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StopReason {
+    /// The run reached its reaction limit
+    RunLimit,
+
+    /// The entropy stayed within the tolerance over the window, see
+    /// `config::Stopping::entropy_window`
+    EntropyPlateau,
+
+    /// No novel product appeared for too long, see `config::Stopping::novelty_collisions`
+    NoNovelty,
+
+    /// The most frequent species did not change for too long, see `config::Stopping::top_k`
+    TopKUnchanged,
+}
+
+/// Why and when a run stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stop {
+    pub reason: StopReason,
+
+    /// Number of reactions run before stopping
+    pub reactions: usize,
+
+    /// Number of collisions run before stopping
+    pub collisions: usize,
+}
+
+/// Checks the stopping criteria of a run at every poll.
+pub struct Detector<'a> {
+    criteria: &'a config::Stopping,
+
+    /// Entropy at the most recent polls, at most `entropy_window` of them
+    entropies: VecDeque<f32>,
+
+    /// Collision count at the last poll that saw a novel product
+    last_novel: usize,
+
+    /// Most frequent species at the last poll, and the number of polls since they changed
+    top_k: Option<HashSet<Term>>,
+    unchanged: usize,
+}
+
+impl<'a> Detector<'a> {
+    /// Start checking `criteria` on `soup`.
+    pub fn new(criteria: &'a config::Stopping, soup: &Soup) -> Self {
+        Detector {
+            criteria,
+            entropies: VecDeque::new(),
+            last_novel: soup.collisions(),
+            top_k: None,
+            unchanged: 0,
+        }
+    }
+
+    /// Update the criteria with the state of `soup` at a poll, and return the first one that
+    /// is met, if any. Novelty is only checked on soups that track it.
+    pub fn check(&mut self, soup: &Soup) -> Option<StopReason> {
+        let mut reason = None;
+
+        if let Some(window) = self.criteria.entropy_window {
+            self.entropies.push_back(soup.population_entropy());
+            if self.entropies.len() > window {
+                self.entropies.pop_front();
+            }
+            let max = self.entropies.iter().copied().fold(f32::MIN, f32::max);
+            let min = self.entropies.iter().copied().fold(f32::MAX, f32::min);
+            if self.entropies.len() == window && max - min <= self.criteria.entropy_tolerance {
+                reason = reason.or(Some(StopReason::EntropyPlateau));
+            }
+        }
+
+        if let Some(collisions) = self.criteria.novelty_collisions {
+            if soup.novel_in_interval().is_some_and(|n| n > 0) {
+                self.last_novel = soup.collisions();
+            }
+            if soup.collisions() - self.last_novel >= collisions {
+                reason = reason.or(Some(StopReason::NoNovelty));
+            }
+        }
+
+        if let Some(k) = self.criteria.top_k {
+            let counts = soup.expression_counts();
+            let top = most_abundant(&counts, k)
+                .into_iter()
+                .cloned()
+                .collect::<HashSet<_>>();
+            if self.top_k.as_ref() == Some(&top) {
+                self.unchanged += 1;
+            } else {
+                self.unchanged = 0;
+                self.top_k = Some(top);
+            }
+            if self.unchanged >= self.criteria.top_k_polls {
+                reason = reason.or(Some(StopReason::TopKUnchanged));
+            }
+        }
+
+        reason
+    }
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StopReason::RunLimit => write!(f, "run-limit"),
+            StopReason::EntropyPlateau => write!(f, "entropy-plateau"),
+            StopReason::NoNovelty => write!(f, "no-novelty"),
+            StopReason::TopKUnchanged => write!(f, "top-k-unchanged"),
+        }
+    }
+}
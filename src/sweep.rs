@@ -0,0 +1,351 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::abundance::{AbundanceRecorder, AbundanceTracker};
+use crate::analysis::Observable;
+use crate::config::{Config, ConfigError};
+use crate::ensemble::Ensemble;
+use crate::generate_expressions_and_seed_soup;
+use crate::soup::Soup;
+use crate::statistics::{self, SeriesAggregator, SummaryOptions};
+
+/// A parameter sweep. Every combination of the values listed in `parameters` is applied to a
+/// base configuration, and each resulting configuration is run `replicates` times.
+///
+/// ```json
+/// {
+///   "parameters": {
+///     "reactor_config.reduction_cutoff": [128, 256, 512],
+///     "reactor_config.size_cutoff": { "start": 256, "end": 1024, "step": 256 },
+///     "reactor_config.rules": [["\\x.\\y.x y"], ["\\x.\\y.y x"]]
+///   },
+///   "replicates": 10
+/// }
+/// ```
+#[warn(missing_docs)]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Sweep {
+    /// Configuration shared by all runs. When set to `None`, the configuration the program was
+    /// started with is used. Default: `None`.
+    #[serde(default)]
+    pub base: Option<Config>,
+
+    /// Values for each swept field, keyed by the dotted path of the field in the configuration
+    /// file, for example `reactor_config.reduction_cutoff`.
+    pub parameters: BTreeMap<String, Values>,
+
+    /// Number of runs for each combination of parameters. Default: `1`.
+    #[serde(default = "default_replicates")]
+    pub replicates: usize,
+}
+
+/// The values taken by a single swept field.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Values {
+    /// An explicit list of values
+    List(Vec<Value>),
+
+    /// Numbers from `start` to `end` inclusive, in increments of `step`
+    Range { start: f64, end: f64, step: f64 },
+}
+
+/// One combination of parameter values, keyed by field path.
+pub type Point = BTreeMap<String, Value>;
+
+/// The result of one run of a sweep.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RunResult {
+    /// The parameter values this run was configured with
+    pub parameters: Point,
+
+    /// Index of the replicate among the runs with the same parameters
+    pub replicate: usize,
+
+    /// The observable at each poll, or only at the end of the run if the configuration does not
+    /// set a polling interval.
+    #[serde(with = "crate::ensemble::nan_as_null::vec")]
+    pub series: Vec<f32>,
+}
+
+#[derive(Debug)]
+pub enum SweepError {
+    UnknownField(String),
+    InvalidRange(String),
+    InvalidConfig(Point, serde_json::Error),
+    OutOfRange(Point, ConfigError),
+    StoredResults(io::Error),
+}
+
+fn default_replicates() -> usize {
+    1
+}
+
+impl Values {
+    /// Expand into the list of values. Whole numbers in ranges are produced as integers, so
+    /// they can be assigned to integer fields.
+    fn expand(&self, field: &str) -> Result<Vec<Value>, SweepError> {
+        match self {
+            Values::List(values) => Ok(values.clone()),
+            Values::Range { start, end, step } => {
+                if *step <= 0.0 || end < start {
+                    return Err(SweepError::InvalidRange(field.to_string()));
+                }
+                let n = ((end - start) / step + 1e-9).floor() as usize + 1;
+                Ok((0..n)
+                    .map(|i| start + step * i as f64)
+                    .map(|x| {
+                        if x.fract() == 0.0 && x.abs() < (1u64 << 53) as f64 {
+                            Value::from(x as i64)
+                        } else {
+                            Value::from(x)
+                        }
+                    })
+                    .collect())
+            }
+        }
+    }
+}
+
+/// Set the field at the dotted `path` in `config` to `value`. The field must already exist, so
+/// that misspelled fields are caught instead of silently ignored.
+fn set_field(config: &mut Value, path: &str, value: Value) -> Result<(), SweepError> {
+    let mut field = config;
+    for key in path.split('.') {
+        field = field
+            .get_mut(key)
+            .ok_or_else(|| SweepError::UnknownField(path.to_string()))?;
+    }
+    *field = value;
+    Ok(())
+}
+
+impl Sweep {
+    /// Create a sweep from a string
+    pub fn from_sweep_str(s: &str) -> serde_json::Result<Sweep> {
+        serde_json::from_str(s)
+    }
+
+    /// Expand the sweep into the cartesian product of its parameters. Each point is returned
+    /// with the full configuration it produces, serialized.
+    pub fn expand(&self, base: &Config) -> Result<Vec<(Point, Value)>, SweepError> {
+        let base = serde_json::to_value(self.base.as_ref().unwrap_or(base))
+            .expect("configuration is always serializable");
+
+        let mut points = vec![Point::new()];
+        for (field, values) in &self.parameters {
+            let values = values.expand(field)?;
+            points = points
+                .into_iter()
+                .flat_map(|point| {
+                    values.iter().map(move |v| {
+                        let mut point = point.clone();
+                        point.insert(field.clone(), v.clone());
+                        point
+                    })
+                })
+                .collect();
+        }
+
+        points
+            .into_iter()
+            .map(|point| {
+                let mut config = base.clone();
+                for (field, value) in &point {
+                    set_field(&mut config, field, value.clone())?;
+                }
+                // Check that the configuration is valid now, rather than in the middle of a run.
+                match serde_json::from_value::<Config>(config.clone()) {
+                    Err(e) => return Err(SweepError::InvalidConfig(point, e)),
+                    Ok(cfg) => {
+                        if let Err(e) = cfg.validate() {
+                            return Err(SweepError::OutOfRange(point, e));
+                        }
+                    }
+                }
+                Ok((point, config))
+            })
+            .collect()
+    }
+
+    /// Run every point of the sweep `replicates` times on `ensemble`, measuring `observable`.
+    /// If there is a `recorder`, the abundances of each run are written for its replicate, the
+    /// index of the run in the order of `expand`.
+    pub fn run(
+        &self,
+        base: &Config,
+        ensemble: &Ensemble,
+        observable: Observable,
+        recorder: Option<AbundanceRecorder>,
+    ) -> Result<impl Iterator<Item = RunResult>, SweepError> {
+        let points = Arc::new(self.expand(base)?);
+        let replicates = self.replicates;
+        let n = points.len() * replicates;
+
+        Ok(ensemble
+            .run(n, move |replicate| {
+                let (point, config) = &points[replicate.id / replicates];
+                let mut cfg: Config = serde_json::from_value(config.clone()).unwrap();
+                cfg.set_seeds(replicate.reactor_seed(), replicate.generator_seed());
+                if observable.needs_novelty() {
+                    cfg.reactor_config.track_novelty = true;
+                }
+
+                let mut soup = generate_expressions_and_seed_soup(&cfg);
+                let mut tracker = recorder.as_ref().map(|_| AbundanceTracker::new());
+                let mut poll = |s: &Soup| {
+                    if let Some(tracker) = &mut tracker {
+                        tracker.record_soup(s);
+                    }
+                    observable.measure(s, &cfg.probe_config)
+                };
+                let series = match cfg.polling_interval {
+                    Some(interval) => {
+                        soup.simulate_and_poll(cfg.run_limit, interval, cfg.verbose_logging, poll)
+                    }
+                    None => {
+                        soup.simulate_for(cfg.run_limit, cfg.verbose_logging);
+                        vec![poll(&soup)]
+                    }
+                };
+                if let (Some(recorder), Some(tracker)) = (&recorder, tracker) {
+                    recorder
+                        .write(replicate.id, tracker, &cfg.reactor_config)
+                        .unwrap_or_else(|e| {
+                            panic!("cannot write abundances of run {}: {}", replicate.id, e)
+                        });
+                }
+
+                RunResult {
+                    parameters: point.clone(),
+                    replicate: replicate.id % replicates,
+                    series,
+                }
+            })
+            .map_err(SweepError::StoredResults)?
+            .map(|(_, result)| result))
+    }
+}
+
+/// Quote a CSV field, doubling any quotes inside it.
+fn csv_quote(field: &str) -> String {
+    format!("\"{}\"", field.replace('"', "\"\""))
+}
+
+/// Write sweep results to `out`, one JSON object per run. If `summary` is set, write one
+/// summary across replicates for each point of the sweep instead.
+pub fn write_results(
+    out: &mut impl Write,
+    results: impl Iterator<Item = RunResult>,
+    summary: Option<statistics::Format>,
+) -> io::Result<()> {
+    let Some(format) = summary else {
+        for result in results {
+            serde_json::to_writer(&mut *out, &result)?;
+            writeln!(out)?;
+        }
+        return Ok(());
+    };
+
+    let mut aggregators = BTreeMap::<String, (Point, SeriesAggregator)>::new();
+    for result in results {
+        let key = serde_json::to_string(&result.parameters)?;
+        aggregators
+            .entry(key)
+            .or_insert_with(|| (result.parameters, SeriesAggregator::new()))
+            .1
+            .push(result.series);
+    }
+
+    let options = SummaryOptions::new();
+    if format == statistics::Format::Csv {
+        writeln!(out, "parameters,{}", statistics::csv_header(&options))?;
+    }
+    for (key, (parameters, aggregator)) in aggregators {
+        let summaries = aggregator.summarize(&options);
+        match format {
+            statistics::Format::Json => {
+                let row = serde_json::json!({ "parameters": parameters, "summary": summaries });
+                writeln!(out, "{}", row)?;
+            }
+            statistics::Format::Csv => {
+                for s in summaries {
+                    writeln!(out, "{},{}", csv_quote(&key), s.to_csv_row())?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for SweepError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SweepError::UnknownField(field) => {
+                write!(
+                    f,
+                    "swept field {} does not exist in the configuration",
+                    field
+                )
+            }
+            SweepError::InvalidRange(field) => {
+                write!(
+                    f,
+                    "range for swept field {} is empty or has a non-positive step",
+                    field
+                )
+            }
+            SweepError::InvalidConfig(point, e) => write!(
+                f,
+                "parameters {} produce an invalid configuration: {}",
+                serde_json::to_string(point).unwrap_or_default(),
+                e
+            ),
+            SweepError::OutOfRange(point, e) => write!(
+                f,
+                "parameters {} produce an invalid configuration: {}",
+                serde_json::to_string(point).unwrap_or_default(),
+                e
+            ),
+            SweepError::StoredResults(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for SweepError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{self, ConfigSeed};
+
+    #[test]
+    fn expand_seeded_base() {
+        let mut base = Config::new();
+        base.reactor_config.seed = ConfigSeed::new([7; 32]);
+        let sweep = serde_json::json!({
+            "parameters": {
+                "reactor_config.reduction_cutoff": [64, 128],
+                "generator_config.BTree.seed": [null, "01".repeat(32)]
+            }
+        });
+        let sweep = Sweep::from_sweep_str(&sweep.to_string()).unwrap();
+        let points = sweep.expand(&base).unwrap();
+        assert_eq!(points.len(), 4);
+        let mut swept = 0;
+        for (_, config) in points {
+            let cfg: Config = serde_json::from_value(config).unwrap();
+            assert_eq!(cfg.reactor_config.seed.seed(), Some([7; 32]));
+            let config::Generator::BTree(generator) = cfg.generator_config else {
+                panic!("expected a BTree generator");
+            };
+            swept += (generator.seed.seed() == Some([1; 32])) as usize;
+        }
+        assert_eq!(swept, 2);
+    }
+}